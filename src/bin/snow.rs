@@ -0,0 +1,26 @@
+use chai::错误;
+use snow::{feihua, qingyun, snow2, snow4};
+
+const 用法: &str = "用法：snow <qingyun|snow2|feihua|snow4> <encode|optimize|…> [参数]";
+
+/// `snow <方案> [参数]`，方案之后的参数和各方案单独的程序相同
+fn main() -> Result<(), 错误> {
+    let mut 参数: Vec<String> = std::env::args().collect();
+    if 参数.len() < 2 {
+        eprintln!("{用法}");
+        std::process::exit(2);
+    }
+    let 方案 = 参数.remove(1);
+    // 让帮助和错误信息中的程序名带上方案
+    参数[0] = format!("snow {方案}");
+    match 方案.as_str() {
+        "qingyun" => qingyun::cli::运行(参数),
+        "snow2" => snow2::cli::运行(参数),
+        "feihua" => feihua::cli::运行(参数),
+        "snow4" => snow4::cli::运行(参数),
+        _ => {
+            eprintln!("未知的方案 {方案}\n{用法}");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+use chai::错误;
+use snow::snow4::cli::运行;
+
+fn main() -> Result<(), 错误> {
+    运行(std::env::args().collect())
+}
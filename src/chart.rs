@@ -0,0 +1,169 @@
+//! 由决策生成 SVG 键位图。
+//!
+//! 各方案把自己的决策整理成 `键盘图`，即每个键上的声母、韵母、主根、副根和归并标注，这里统一排版为 SVG。
+//! 字根的字形先经过 `get_pua_mapper` 转换，查看时需要安装相应的字根字体。
+
+use crate::common::get_pua_mapper;
+use rustc_hash::FxHashMap;
+use std::{fs::write, path::PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum 标注类别 {
+    声母,
+    韵母,
+    主根,
+    副根,
+    归并,
+}
+
+impl 标注类别 {
+    fn 颜色(&self) -> &'static str {
+        match self {
+            标注类别::声母 => "#c0392b",
+            标注类别::韵母 => "#2471a3",
+            标注类别::主根 => "#000000",
+            标注类别::副根 => "#1e8449",
+            标注类别::归并 => "#808b96",
+        }
+    }
+
+    fn 字号(&self) -> usize {
+        match self {
+            标注类别::主根 => 20,
+            _ => 14,
+        }
+    }
+}
+
+const 键盘行: [&str; 3] = ["qwertyuiop", "asdfghjkl;", "zxcvbnm,./"];
+const 键宽: usize = 180;
+const 键间距: usize = 8;
+const 行高: usize = 24;
+const 边距: usize = 20;
+
+pub struct 键盘图 {
+    pub 标题: String,
+    标注: FxHashMap<char, Vec<(标注类别, String)>>,
+    字形映射: FxHashMap<char, char>,
+}
+
+impl 键盘图 {
+    pub fn 新建(标题: String) -> Self {
+        Self {
+            标题,
+            标注: FxHashMap::default(),
+            字形映射: get_pua_mapper(),
+        }
+    }
+
+    /// 把字根名称转换为字体中的字形
+    pub fn 字形(&self, 名称: &str) -> String {
+        名称
+            .chars()
+            .map(|x| self.字形映射.get(&x).cloned().unwrap_or(x))
+            .collect()
+    }
+
+    pub fn 添加(&mut self, 键: char, 类别: 标注类别, 文本: String) {
+        self.标注.entry(键).or_default().push((类别, 文本));
+    }
+
+    /// 把一个键上的标注按类别排好，再按键宽折行
+    fn 排版(&self, 键: char) -> Vec<Vec<(标注类别, String)>> {
+        let mut 标注 = self.标注.get(&键).cloned().unwrap_or_default();
+        标注.sort();
+        let mut 行列表: Vec<Vec<(标注类别, String)>> = vec![];
+        let mut 当前宽度 = 键宽;
+        let mut 上一类别 = None;
+        for (类别, 文本) in 标注 {
+            let 宽度 = 文本
+                .chars()
+                .map(|x| {
+                    if x.is_ascii() {
+                        类别.字号() / 2 + 1
+                    } else {
+                        类别.字号()
+                    }
+                })
+                .sum::<usize>()
+                + 6;
+            if 上一类别 != Some(类别) || 当前宽度 + 宽度 > 键宽 - 12 {
+                行列表.push(vec![]);
+                当前宽度 = 0;
+            }
+            当前宽度 += 宽度;
+            上一类别 = Some(类别);
+            行列表.last_mut().unwrap().push((类别, 文本));
+        }
+        行列表
+    }
+
+    pub fn 渲染(&self) -> String {
+        let 排版结果: FxHashMap<char, _> = 键盘行
+            .iter()
+            .flat_map(|x| x.chars())
+            .map(|键| (键, self.排版(键)))
+            .collect();
+        let 最多行数 = 排版结果.values().map(|x| x.len()).max().unwrap_or(0);
+        let 键高 = 行高 * (最多行数 + 1) + 键间距;
+        let 宽度 = 边距 * 2 + 键宽 * 10 + 键间距 * 9 + 键宽 / 2;
+        let 高度 = 边距 * 2 + 40 + (键高 + 键间距) * 键盘行.len();
+        let mut 内容 = vec![
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{宽度}" height="{高度}" viewBox="0 0 {宽度} {高度}" font-family="sans-serif">"#
+            ),
+            format!(r#"<rect width="{宽度}" height="{高度}" fill="white"/>"#),
+            format!(
+                r#"<text x="{边距}" y="{}" font-size="22" font-weight="bold">{}</text>"#,
+                边距 + 22,
+                转义(&self.标题)
+            ),
+        ];
+        for (行号, 行) in 键盘行.iter().enumerate() {
+            let 偏移 = 边距 + 行号 * 键宽 / 4;
+            let 顶 = 边距 + 40 + 行号 * (键高 + 键间距);
+            for (列号, 键) in 行.chars().enumerate() {
+                let 左 = 偏移 + 列号 * (键宽 + 键间距);
+                内容.push(format!(
+                    r##"<rect x="{左}" y="{顶}" width="{键宽}" height="{键高}" rx="8" fill="#fafafa" stroke="#999"/>"##
+                ));
+                内容.push(format!(
+                    r##"<text x="{}" y="{}" font-size="18" font-weight="bold" fill="#555">{}</text>"##,
+                    左 + 8,
+                    顶 + 行高,
+                    转义(&键.to_uppercase().to_string())
+                ));
+                for (序号, 标注行) in 排版结果[&键].iter().enumerate() {
+                    let 基线 = 顶 + 行高 * (序号 + 2);
+                    let mut 片段 = vec![];
+                    for (类别, 文本) in 标注行 {
+                        片段.push(format!(
+                            r#"<tspan fill="{}" font-size="{}">{}</tspan>"#,
+                            类别.颜色(),
+                            类别.字号(),
+                            转义(文本)
+                        ));
+                    }
+                    内容.push(format!(
+                        r#"<text x="{}" y="{基线}" xml:space="preserve">{}</text>"#,
+                        左 + 8,
+                        片段.join(" ")
+                    ));
+                }
+            }
+        }
+        内容.push("</svg>".to_string());
+        内容.join("\n")
+    }
+
+    pub fn 写入(&self, 路径: &PathBuf) -> std::io::Result<()> {
+        write(路径, self.渲染())
+    }
+}
+
+fn 转义(文本: &str) -> String {
+    文本
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -1,6 +1,28 @@
-use rustc_hash::FxHashMap;
+use crate::chart::键盘图;
+use crate::exporters::导出配置;
+use chai::config::{SolverConfig, 配置};
+use chai::contexts::上下文;
+use chai::encoders::编码器;
+use chai::interfaces::command_line::{
+    从命令行参数创建, 命令, 命令行, 默认命令行参数
+};
+use chai::interfaces::默认输入;
+use chai::objectives::目标函数;
+use chai::operators::变异;
+use chai::optimizers::决策;
+use chai::{元素, 错误};
+use clap::Parser;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::de::DeserializeOwned;
 use serde_yaml::from_str;
-use std::fs::read_to_string;
+use std::{
+    fmt::Display,
+    fs::{File, create_dir_all, read_to_string, write},
+    io::{Error, ErrorKind, Write},
+    path::PathBuf,
+    sync::OnceLock,
+    thread::scope,
+};
 
 /// 定义通用的转换 trait
 
@@ -9,6 +31,321 @@ pub trait 转换 {
     fn 编码空间大小() -> usize;
 }
 
+/// 由每个编码对象（字、词或字块）涉及的元素，构建元素到对象序号的映射，用于按决策的变化增量编码
+pub fn 构建关联映射<I: IntoIterator<Item = 元素>>(
+    对象列表: impl Iterator<Item = I>,
+) -> FxHashMap<元素, Vec<usize>> {
+    let mut 关联映射: FxHashMap<元素, Vec<usize>> = FxHashMap::default();
+    for (序号, 元素列表) in 对象列表.enumerate() {
+        let 元素集合: FxHashSet<_> = 元素列表.into_iter().filter(|x| *x != 0).collect();
+        for 元素 in 元素集合 {
+            关联映射.entry(元素).or_default().push(序号);
+        }
+    }
+    关联映射
+}
+
+/// 和元素集合中任一元素关联的对象序号，从小到大排列
+pub fn 关联序号(
+    关联映射: &FxHashMap<元素, Vec<usize>>,
+    元素集合: &FxHashSet<元素>,
+) -> Vec<usize> {
+    let mut 序号列表: Vec<_> = 元素集合
+        .iter()
+        .filter_map(|x| 关联映射.get(x))
+        .flatten()
+        .cloned()
+        .collect();
+    序号列表.sort_unstable();
+    序号列表.dedup();
+    序号列表
+}
+
+/// 把直接或间接归并到集合中元素的元素也加入集合，`归并关系` 给出每个归并的元素和它归并到的元素
+pub fn 展开归并(
+    元素集合: &mut FxHashSet<元素>,
+    归并关系: impl Iterator<Item = (元素, 元素)> + Clone,
+) {
+    loop {
+        let 依赖: Vec<_> = 归并关系
+            .clone()
+            .filter(|(元素, 目标)| !元素集合.contains(元素) && 元素集合.contains(目标))
+            .map(|(元素, _)| 元素)
+            .collect();
+        if 依赖.is_empty() {
+            break;
+        }
+        元素集合.extend(依赖);
+    }
+}
+
+/// 在 chai 默认命令行参数的基础上，增加冰雪系列方案共用的选项
+#[derive(Parser)]
+pub struct 冰雪命令行参数 {
+    #[command(flatten)]
+    pub 默认: 默认命令行参数,
+    /// 优化时根据各变异操作近期的接受率和改进率自适应地调整选择概率
+    #[arg(long, global = true)]
+    pub adaptive: bool,
+    /// 变异操作的随机数种子，各线程的种子由它派生；不指定时随机生成，并记录在输出目录中。
+    /// 退火的接受判定使用 chai 内部的随机数，相同的种子不保证得到相同的结果
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// 导出配置文件，指定编码和优化结束后运行哪些码表导出器
+    #[arg(long, global = true)]
+    pub exporters: Option<PathBuf>,
+    /// 字根私用区字形映射文件，默认为当前目录下的 pua_mapper.yaml
+    #[arg(long, global = true)]
+    pub pua_mapper: Option<PathBuf>,
+    /// 方案的选项文件，冰雪飞花默认为 feihua/options.yaml，冰雪四拼默认为 snow4/options.yaml
+    #[arg(long, global = true)]
+    pub options: Option<PathBuf>,
+}
+
+impl 冰雪命令行参数 {
+    pub fn 种子(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    pub fn 导出配置(&self, 默认: &[&str]) -> std::io::Result<导出配置> {
+        导出配置::读取(self.exporters.as_ref(), 默认)
+    }
+
+    /// 指定了字形映射文件时，让之后的 `get_pua_mapper` 都读取它
+    pub fn 应用字形映射(&self) {
+        if let Some(路径) = &self.pua_mapper {
+            字形映射路径.set(路径.clone()).unwrap();
+        }
+    }
+}
+
+/// 由总种子为每个线程派生互不相关的种子（SplitMix64）
+pub fn 派生种子(种子: u64, 线程序号: usize) -> u64 {
+    let mut z = 种子.wrapping_add((线程序号 as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// 把总种子和各线程的种子写入输出目录的 种子.txt
+pub fn 记录种子(输出目录: &PathBuf, 种子: u64, 线程数: usize) -> Result<(), 错误> {
+    let mut 内容 = format!("变异操作的种子：{种子}（退火的接受判定不受种子控制）\n");
+    for 线程序号 in 0..线程数 {
+        内容 += &format!("线程 {线程序号}：{}\n", 派生种子(种子, 线程序号));
+    }
+    write(输出目录.join("种子.txt"), 内容)?;
+    Ok(())
+}
+
+/// 读取并解析 YAML 文件，错误信息中带有文件路径
+pub fn 解析文件<T: DeserializeOwned>(路径: &PathBuf) -> Result<T, 错误> {
+    let 内容 = read_to_string(路径)
+        .map_err(|e| Error::new(e.kind(), format!("无法读取 {}：{e}", 路径.display())))?;
+    let 结果 = from_str(&内容).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("无法解析 {}：{e}", 路径.display()),
+        )
+    })?;
+    Ok(结果)
+}
+
+/// 读取 YAML 选项文件。指定的文件必须存在；没有指定时默认文件可以不存在，此时全部使用默认值
+pub fn 读取选项<T: DeserializeOwned + Default>(
+    路径: Option<&PathBuf>,
+    默认路径: &str,
+) -> Result<T, 错误> {
+    match 路径 {
+        Some(路径) => 解析文件(路径),
+        None => {
+            let 默认路径 = PathBuf::from(默认路径);
+            if 默认路径.exists() {
+                解析文件(&默认路径)
+            } else {
+                Ok(T::default())
+            }
+        }
+    }
+}
+
+/// 一个方案的上下文、编码器、目标函数和变异操作，以及编码和优化后写出哪些文件。
+/// 实现了这个 trait 的方案可以直接用 `运行方案` 得到 encode 和 optimize 两个命令。
+pub trait 方案: Sized + Sync {
+    type 决策: 决策 + Send + Sync;
+    type 上下文: 上下文<决策 = Self::决策> + Sync;
+    type 编码器: 编码器<决策 = Self::决策>;
+    type 目标函数: 目标函数<决策 = Self::决策, 目标值: Display + Send>;
+    type 操作: 变异<决策 = Self::决策>;
+
+    /// 没有导出配置时使用的导出器
+    const 默认导出器: &'static [&'static str];
+
+    fn 新建(
+        命令行参数: &冰雪命令行参数, 输入: 默认输入
+    ) -> Result<Self, 错误>;
+    fn 上下文(&self) -> &Self::上下文;
+    fn 配置(&self) -> &配置;
+    fn 初始决策(&self) -> &Self::决策;
+    /// `输出` 为真时编码器用于写出码表，可以做优化时不需要的工作
+    fn 新建编码器(&self, 输出: bool) -> Result<Self::编码器, 错误>;
+    fn 新建目标函数(&self, 编码器: Self::编码器) -> Result<Self::目标函数, 错误>;
+    /// 每个线程的变异操作，可以在线程的输出目录下记录变异的情况
+    fn 新建操作(
+        &self,
+        目标函数: &mut Self::目标函数,
+        种子: u64,
+        子命令行: &命令行,
+    ) -> Self::操作;
+    /// 写出码表和导出器生成的文件，目标函数已经对要输出的决策计算过
+    fn 输出码表(
+        &self,
+        目标函数: &Self::目标函数,
+        输出目录: &PathBuf,
+        导出器列表: &[String],
+    ) -> Result<(), 错误>;
+    fn 分析码表(
+        &self, 目标函数: &Self::目标函数, 输出目录: &PathBuf
+    ) -> Result<(), 错误>;
+    fn 生成键盘图(&self, 决策: &Self::决策) -> 键盘图;
+
+    /// 编码时的输出目录，默认为 `output`
+    fn 编码输出目录(&self, _参数: 默认命令行参数) -> Result<PathBuf, 错误> {
+        let 输出目录 = PathBuf::from("output");
+        create_dir_all(&输出目录)?;
+        Ok(输出目录)
+    }
+
+    /// 写出不在码表中的决策信息，例如序列化的方案；`优化后` 区分编码和优化
+    fn 写出决策(
+        &self,
+        _决策: &Self::决策,
+        _输出目录: &PathBuf,
+        _优化后: bool,
+    ) -> Result<(), 错误> {
+        Ok(())
+    }
+
+    /// 优化结束后写出变异操作的记录
+    fn 写出操作记录(
+        &self, _操作: &Self::操作, _输出目录: &PathBuf
+    ) -> Result<(), 错误> {
+        Ok(())
+    }
+
+    /// 写出一个决策的所有结果：决策信息、码表、分析和键位图
+    fn 输出(
+        &self,
+        决策: &Self::决策,
+        输出目录: &PathBuf,
+        导出器列表: &[String],
+        优化后: bool,
+    ) -> Result<(Self::目标函数, <Self::目标函数 as 目标函数>::目标值, f64), 错误> {
+        let 编码器 = self.新建编码器(true)?;
+        let mut 目标函数 = self.新建目标函数(编码器)?;
+        let (指标, 分数) = 目标函数.计算(决策, &None);
+        self.写出决策(决策, 输出目录, 优化后)?;
+        self.输出码表(&目标函数, 输出目录, 导出器列表)?;
+        self.分析码表(&目标函数, 输出目录)?;
+        self.生成键盘图(决策).写入(&输出目录.join("键位图.svg"))?;
+        Ok((目标函数, 指标, 分数))
+    }
+}
+
+/// 解析命令行参数并运行方案的 encode 或 optimize，`参数` 的第一项是程序名
+pub fn 运行方案<S: 方案>(参数: Vec<String>) -> Result<(), 错误> {
+    let 命令行参数 = 冰雪命令行参数::parse_from(参数);
+    命令行参数.应用字形映射();
+    let 种子 = 命令行参数.种子();
+    let 导出配置 = 命令行参数.导出配置(S::默认导出器)?;
+    let 输入 = 从命令行参数创建(&命令行参数.默认);
+    let 方案 = S::新建(&命令行参数, 输入)?;
+    let 参数 = 命令行参数.默认;
+    match 参数.command {
+        命令::Encode { .. } => {
+            let 输出目录 = 方案.编码输出目录(参数)?;
+            let (_, 指标, 分数) =
+                方案.输出(方案.初始决策(), &输出目录, &导出配置.编码后, false)?;
+            println!("分数：{分数:.4}；{指标}");
+            println!("输出目录：{}", 输出目录.display());
+        }
+        命令::Optimize { threads, .. } => {
+            let 命令行 = 命令行::新建(参数, None);
+            多线程优化(&方案, &命令行, threads, 种子, &导出配置.优化后)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 各方案共用的多线程退火。每个线程优化结束后重新编码最优解（退火结束时的状态不一定是最优解），
+/// 在线程的输出目录下写出结果；最后把各线程的分数和指标按分数排序，打印并写入 总结.txt
+pub fn 多线程优化<S: 方案>(
+    方案: &S,
+    命令行: &命令行,
+    线程数: usize,
+    种子: u64,
+    导出器列表: &[String],
+) -> Result<(), 错误> {
+    let SolverConfig::SimulatedAnnealing(退火) = 方案
+        .配置()
+        .clone()
+        .optimization
+        .unwrap()
+        .metaheuristic
+        .unwrap();
+    记录种子(&命令行.输出目录, 种子, 线程数)?;
+    let mut 优化结果列表 = scope(|s| {
+        let 线程池: Vec<_> = (0..线程数)
+            .map(|线程序号| {
+                let 优化方法 = 退火.clone();
+                let 子命令行 = 命令行.生成子命令行(线程序号);
+                s.spawn(move || -> Result<_, 错误> {
+                    let 编码器 = 方案.新建编码器(false)?;
+                    let mut 目标函数 = 方案.新建目标函数(编码器)?;
+                    let mut 操作 =
+                        方案.新建操作(&mut 目标函数, 派生种子(种子, 线程序号), &子命令行);
+                    let 优化结果 = 优化方法.优化(
+                        方案.初始决策(),
+                        &mut 目标函数,
+                        &mut 操作,
+                        方案.上下文(),
+                        &子命令行,
+                    );
+                    let 输出目录 = &子命令行.输出目录;
+                    方案.写出操作记录(&操作, 输出目录)?;
+                    方案.输出(&优化结果.映射, 输出目录, 导出器列表, true)?;
+                    Ok((线程序号, 优化结果))
+                })
+            })
+            .collect();
+        线程池
+            .into_iter()
+            .map(|x| x.join().unwrap())
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    优化结果列表.sort_by(|a, b| a.1.分数.partial_cmp(&b.1.分数).unwrap());
+    let mut 总结文件 = File::create(命令行.输出目录.join("总结.txt"))?;
+    writeln!(总结文件, "变异操作的种子：{种子}")?;
+    for (线程序号, 优化结果) in 优化结果列表 {
+        print!(
+            "线程 {} 分数：{:.4}；{}",
+            线程序号, 优化结果.分数, 优化结果.指标
+        );
+        write!(
+            总结文件,
+            "线程 {} 分数：{:.4}；{}",
+            线程序号, 优化结果.分数, 优化结果.指标
+        )?;
+    }
+    Ok(())
+}
+
+static 字形映射路径: OnceLock<PathBuf> = OnceLock::new();
+
 pub fn get_pua_mapper() -> FxHashMap<char, char> {
-    from_str(&read_to_string("pua_mapper.yaml").unwrap()).unwrap()
+    let 路径 = 字形映射路径
+        .get()
+        .cloned()
+        .unwrap_or_else(|| "pua_mapper.yaml".into());
+    from_str(&read_to_string(路径).unwrap()).unwrap()
 }
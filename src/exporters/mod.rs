@@ -0,0 +1,374 @@
+//! 各方案共用的码表导出。
+//!
+//! 方案把编码结果整理成 `导出码表`，再交给配置中选定的导出器写出各种格式的文件。
+
+pub mod rime;
+
+use crate::exporters::rime::Rime导出器;
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use serde_yaml::from_str;
+use std::{
+    fs::{File, read_to_string},
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// 键盘上的码元顺序，用于生成各平台的按键表
+const 码元顺序: &str = "qwertyuiopasdfghjkl;zxcvbnm,./'[]-=1234567890";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum 条目类型 {
+    全码,
+    简码,
+    简词,
+}
+
+/// 码表中的一条记录，编码中的 `_` 表示空格
+#[derive(Debug, Clone)]
+pub struct 导出条目 {
+    pub 词: String,
+    pub 编码: String,
+    pub 权重: u64,
+    pub 类型: 条目类型,
+}
+
+impl 导出条目 {
+    pub fn 无空格编码(&self) -> String {
+        self.编码.replace("_", "")
+    }
+
+    pub fn 带空格编码(&self) -> String {
+        self.编码.replace("_", " ")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct 导出码表 {
+    /// 方案的显示名，如「冰雪清韵」
+    pub 名称: String,
+    /// 方案的 ASCII 标识，如 `snow_qingyun`，用作文件名
+    pub 标识: String,
+    /// 按输出顺序排列的条目
+    pub 条目列表: Vec<导出条目>,
+    /// 繁体条目，没有单独的繁体码表时为空
+    pub 繁体条目列表: Vec<导出条目>,
+    /// 编码到按优先级排列的词列表，编码中不含空格；为空时表示方案没有固态词典
+    pub 固态词典: Vec<(String, Vec<String>)>,
+    /// 繁体方案的固态词典，格式同上
+    pub 繁体固态词典: Vec<(String, Vec<String>)>,
+    /// 词到拆分的映射
+    pub 拆分列表: Vec<(String, String)>,
+    /// 大竹码表的文件名，为空时为 `大竹码表.txt`
+    pub 大竹文件名: String,
+    /// 方案按自己的写法和顺序整理好的大竹码表，每行为编码一栏和词；
+    /// 为空时按条目列表的顺序生成，拆分在后
+    pub 大竹码表: Vec<(String, String)>,
+}
+
+impl 导出码表 {
+    /// 码表中用到的所有码元，按键盘顺序排列
+    pub fn 字母表(&self) -> String {
+        let mut 字母表: Vec<char> = self
+            .条目列表
+            .iter()
+            .chain(&self.繁体条目列表)
+            .flat_map(|x| x.无空格编码().chars().collect::<Vec<_>>())
+            .chain(
+                self.固态词典
+                    .iter()
+                    .chain(&self.繁体固态词典)
+                    .flat_map(|(x, _)| x.chars().collect::<Vec<_>>()),
+            )
+            .collect();
+        字母表.sort_by_key(|x| 码元顺序.find(*x).unwrap_or(usize::MAX));
+        字母表.dedup();
+        字母表.into_iter().collect()
+    }
+
+    pub fn 最大码长(&self) -> usize {
+        self.条目列表
+            .iter()
+            .map(|x| x.无空格编码().chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// 固态词典；方案没有提供时按条目列表的顺序归并
+    fn 按编码归并(&self) -> Vec<(String, Vec<String>)> {
+        if !self.固态词典.is_empty() {
+            return self.固态词典.clone();
+        }
+        let mut 结果: Vec<(String, Vec<String>)> = vec![];
+        let mut 位置 = FxHashMap::default();
+        for 条目 in &self.条目列表 {
+            let 编码 = 条目.无空格编码();
+            let 序号 = *位置.entry(编码.clone()).or_insert_with(|| {
+                结果.push((编码, vec![]));
+                结果.len() - 1
+            });
+            结果[序号].1.push(条目.词.clone());
+        }
+        结果
+    }
+}
+
+pub trait 码表导出器: Send + Sync {
+    /// 在配置中引用这个导出器时使用的名称
+    fn 名称(&self) -> &'static str;
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()>;
+}
+
+fn 写入行(路径: PathBuf, 行列表: impl IntoIterator<Item = String>) -> std::io::Result<()> {
+    let mut 文件 = File::create(路径)?;
+    for 行 in 行列表 {
+        writeln!(文件, "{行}")?;
+    }
+    Ok(())
+}
+
+/// 测评用的「词 编码」码表，编码不含空格
+pub struct 测评导出器;
+
+impl 码表导出器 for 测评导出器 {
+    fn 名称(&self) -> &'static str {
+        "测评"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 行列表 = 码表
+            .条目列表
+            .iter()
+            .map(|x| format!("{}\t{}", x.词, x.无空格编码()));
+        写入行(目录.join(format!("{}.txt", 码表.名称)), 行列表)
+    }
+}
+
+/// 大竹查码用的「编码 词」码表，含拆分；方案可以提供自己的写法和顺序
+pub struct 大竹导出器;
+
+impl 码表导出器 for 大竹导出器 {
+    fn 名称(&self) -> &'static str {
+        "大竹"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 文件名 = if 码表.大竹文件名.is_empty() {
+            "大竹码表.txt"
+        } else {
+            &码表.大竹文件名
+        };
+        if !码表.大竹码表.is_empty() {
+            let 行列表 = 码表
+                .大竹码表
+                .iter()
+                .map(|(编码, 词)| format!("{编码}\t{词}"));
+            return 写入行(目录.join(文件名), 行列表);
+        }
+        let 编码行 = 码表
+            .条目列表
+            .iter()
+            .map(|x| format!("{}\t{}", x.编码, x.词));
+        let 拆分行 = 码表
+            .拆分列表
+            .iter()
+            .map(|(词, 拆分)| format!("拆分［{拆分}］\t{词}"));
+        写入行(目录.join(文件名), 编码行.chain(拆分行))
+    }
+}
+
+/// 形码盒子测评用的码表，只含单字，空格写作空格
+pub struct 形码盒子导出器;
+
+impl 码表导出器 for 形码盒子导出器 {
+    fn 名称(&self) -> &'static str {
+        "形码盒子"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 行列表 = 码表
+            .条目列表
+            .iter()
+            .filter(|x| x.类型 != 条目类型::简词)
+            .map(|x| format!("{}\t{}", x.词, x.带空格编码()));
+        写入行(目录.join("形码盒子测评码表.txt"), 行列表)
+    }
+}
+
+/// 「编码 词1 词2 ……」格式的固态词典
+pub struct 固态词典导出器;
+
+impl 码表导出器 for 固态词典导出器 {
+    fn 名称(&self) -> &'static str {
+        "固态词典"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 行列表 = 码表
+            .按编码归并()
+            .into_iter()
+            .map(|(编码, 词列表)| format!("{编码}\t{}", 词列表.join(" ")));
+        写入行(目录.join(format!("{}.fixed.txt", 码表.标识)), 行列表)
+    }
+}
+
+/// Fcitx5 码表的源文件，可以用 `libime_tabledict` 编译为 `.main.dict`
+pub struct Fcitx5导出器;
+
+impl 码表导出器 for Fcitx5导出器 {
+    fn 名称(&self) -> &'static str {
+        "fcitx5"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 码长 = 码表.最大码长();
+        let mut 行列表 = vec![
+            format!("KeyCode={}", 码表.字母表()),
+            format!("Length={码长}"),
+            "[Data]".to_string(),
+        ];
+        for (编码, 词列表) in 码表.按编码归并() {
+            for 词 in 词列表 {
+                行列表.push(format!("{编码} {词}"));
+            }
+        }
+        写入行(目录.join(format!("{}.fcitx5.txt", 码表.标识)), 行列表)
+    }
+}
+
+/// 小小输入法的码表文本
+pub struct 小小导出器;
+
+impl 码表导出器 for 小小导出器 {
+    fn 名称(&self) -> &'static str {
+        "小小"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let mut 行列表 = vec![
+            format!("name={}", 码表.名称),
+            format!("key={}", 码表.字母表()),
+            format!("len={}", 码表.最大码长()),
+            "[DATA]".to_string(),
+        ];
+        for (编码, 词列表) in 码表.按编码归并() {
+            行列表.push(format!("{编码} {}", 词列表.join(" ")));
+        }
+        写入行(目录.join(format!("{}.yong.txt", 码表.标识)), 行列表)
+    }
+}
+
+fn 转义(文本: &str) -> String {
+    文本
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// macOS 文本替换格式的 plist，可以直接拖入「键盘 - 文本替换」中导入。
+/// 这不是输入法码表：每个编码的每个词都是一条替换，输入编码后按空格替换为词
+pub struct 文本替换导出器;
+
+impl 码表导出器 for 文本替换导出器 {
+    fn 名称(&self) -> &'static str {
+        "文本替换"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let mut 行列表 = vec![
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+            r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#.to_string(),
+            r#"<plist version="1.0">"#.to_string(),
+            "<array>".to_string(),
+        ];
+        for (编码, 词列表) in 码表.按编码归并() {
+            for 词 in 词列表 {
+                行列表.push(format!(
+                    "\t<dict>\n\t\t<key>phrase</key>\n\t\t<string>{}</string>\n\t\t<key>shortcut</key>\n\t\t<string>{}</string>\n\t</dict>",
+                    转义(&词),
+                    转义(&编码.replace("_", ""))
+                ));
+            }
+        }
+        行列表.push("</array>".to_string());
+        行列表.push("</plist>".to_string());
+        写入行(目录.join(format!("{}.文本替换.plist", 码表.标识)), 行列表)
+    }
+}
+
+pub fn 全部导出器() -> Vec<Box<dyn 码表导出器>> {
+    vec![
+        Box::new(测评导出器),
+        Box::new(大竹导出器),
+        Box::new(形码盒子导出器),
+        Box::new(固态词典导出器),
+        Box::new(Rime导出器),
+        Box::new(Fcitx5导出器),
+        Box::new(小小导出器),
+        Box::new(文本替换导出器),
+    ]
+}
+
+pub fn 查找导出器(名称: &str) -> Option<Box<dyn 码表导出器>> {
+    全部导出器().into_iter().find(|x| x.名称() == 名称)
+}
+
+/// 导出配置文件的内容，例如
+///
+/// ```yaml
+/// encode: [测评, 大竹, rime]
+/// optimize: [测评]
+/// ```
+///
+/// 缺少的字段使用方案的默认导出器。
+#[derive(Debug, Clone, Default, Deserialize)]
+struct 原始导出配置 {
+    encode: Option<Vec<String>>,
+    optimize: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct 导出配置 {
+    pub 编码后: Vec<String>,
+    pub 优化后: Vec<String>,
+}
+
+impl 导出配置 {
+    /// 读取导出配置；路径为空时使用方案的默认导出器。配置中出现未知的导出器时报错。
+    pub fn 读取(路径: Option<&PathBuf>, 默认: &[&str]) -> std::io::Result<Self> {
+        let 原始: 原始导出配置 = match 路径 {
+            Some(路径) => from_str(&read_to_string(路径)?)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            None => 原始导出配置::default(),
+        };
+        let 默认: Vec<String> = 默认.iter().map(|x| x.to_string()).collect();
+        let 配置 = Self {
+            编码后: 原始.encode.unwrap_or_else(|| 默认.clone()),
+            优化后: 原始.optimize.unwrap_or(默认),
+        };
+        for 名称 in 配置.编码后.iter().chain(&配置.优化后) {
+            if 查找导出器(名称).is_none() {
+                let 可用: Vec<_> = 全部导出器().iter().map(|x| x.名称()).collect();
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("未知的导出器 {名称}，可用的导出器有 {}", 可用.join("、")),
+                ));
+            }
+        }
+        Ok(配置)
+    }
+}
+
+/// 依次运行给定的导出器
+pub fn 导出(
+    码表: &导出码表, 导出器列表: &[String], 目录: &Path
+) -> std::io::Result<()> {
+    for 名称 in 导出器列表 {
+        if let Some(导出器) = 查找导出器(名称) {
+            导出器.导出(码表, 目录)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Rime 输入方案导出。
+//!
+//! 生成的 `rime` 目录可以直接复制到 Rime 的用户目录中部署，包含：
+//!
+//! - `<标识>.schema.yaml`、`<标识>.dict.yaml`：简体方案和按权重排序的词典
+//! - `<标识>_tc.schema.yaml`、`<标识>_tc.dict.yaml`：繁体方案和词典（方案提供繁体条目时）
+//! - `<标识>.fixed.dict.yaml`：固态词典，按原始顺序排列，包含简码优先级、简词快符和数字等条目
+//! - `<标识>_tc.fixed.dict.yaml`：繁体方案的固态词典（方案提供时）
+
+use crate::exporters::{导出条目, 导出码表, 码表导出器};
+use chrono::Local;
+use std::{
+    fs::{File, create_dir_all},
+    io::Write,
+    path::Path,
+};
+
+pub struct Rime导出器;
+
+fn 版本() -> String {
+    format!("{}", Local::now().format("%Y-%m-%d"))
+}
+
+fn 写入加权词典(
+    目录: &Path, 名称: &str, 条目列表: &[导出条目]
+) -> std::io::Result<()> {
+    let mut 文件 = File::create(目录.join(format!("{名称}.dict.yaml")))?;
+    writeln!(
+        文件,
+        "# Rime dictionary\n# encoding: utf-8\n#\n# 由 snow 自动生成\n"
+    )?;
+    writeln!(文件, "---\nname: {名称}\nversion: \"{}\"", 版本())?;
+    writeln!(
+        文件,
+        "sort: by_weight\ncolumns:\n  - text\n  - code\n  - weight\n...\n"
+    )?;
+    for 条目 in 条目列表 {
+        writeln!(文件, "{}\t{}\t{}", 条目.词, 条目.无空格编码(), 条目.权重)?;
+    }
+    Ok(())
+}
+
+fn 写入固态词典(
+    目录: &Path,
+    名称: &str,
+    固态词典: &[(String, Vec<String>)],
+) -> std::io::Result<()> {
+    let mut 文件 = File::create(目录.join(format!("{名称}.dict.yaml")))?;
+    writeln!(
+        文件,
+        "# Rime dictionary\n# encoding: utf-8\n#\n# 由 snow 自动生成\n"
+    )?;
+    writeln!(文件, "---\nname: {名称}\nversion: \"{}\"", 版本())?;
+    writeln!(文件, "sort: original\ncolumns:\n  - text\n  - code\n...\n")?;
+    for (编码, 词列表) in 固态词典 {
+        for 词 in 词列表 {
+            writeln!(文件, "{词}\t{编码}")?;
+        }
+    }
+    Ok(())
+}
+
+fn 写入方案(
+    目录: &Path,
+    方案名: &str,
+    显示名: &str,
+    字母表: &str,
+    最大码长: usize,
+    固态词典: Option<&str>,
+) -> std::io::Result<()> {
+    let mut 文件 = File::create(目录.join(format!("{方案名}.schema.yaml")))?;
+    let 固态翻译器 = if 固态词典.is_some() {
+        "    - table_translator@fixed\n"
+    } else {
+        ""
+    };
+    write!(
+        文件,
+        r#"# Rime schema
+# encoding: utf-8
+#
+# 由 snow 自动生成
+
+schema:
+  schema_id: {方案名}
+  name: {显示名}
+  version: "{版本}"
+
+switches:
+  - name: ascii_mode
+    reset: 0
+    states: [ 中文, 西文 ]
+  - name: full_shape
+    states: [ 半角, 全角 ]
+
+engine:
+  processors:
+    - ascii_composer
+    - recognizer
+    - key_binder
+    - speller
+    - punctuator
+    - selector
+    - navigator
+    - express_editor
+  segmentors:
+    - ascii_segmentor
+    - matcher
+    - abc_segmentor
+    - punct_segmentor
+    - fallback_segmentor
+  translators:
+    - punct_translator
+{固态翻译器}    - table_translator
+  filters:
+    - uniquifier
+
+speller:
+  alphabet: "{字母表}"
+  max_code_length: {最大码长}
+
+translator:
+  dictionary: {方案名}
+  enable_charset_filter: false
+  enable_sentence: false
+  enable_completion: false
+  enable_user_dict: false
+
+punctuator:
+  import_preset: default
+
+key_binder:
+  import_preset: default
+
+recognizer:
+  import_preset: default
+"#,
+        版本 = 版本(),
+    )?;
+    if let Some(固态词典) = 固态词典 {
+        write!(
+            文件,
+            r#"
+fixed:
+  dictionary: {固态词典}
+  initial_quality: 1000
+  enable_charset_filter: false
+  enable_sentence: false
+  enable_completion: false
+  enable_user_dict: false
+"#
+        )?;
+    }
+    Ok(())
+}
+
+impl 码表导出器 for Rime导出器 {
+    fn 名称(&self) -> &'static str {
+        "rime"
+    }
+
+    fn 导出(&self, 码表: &导出码表, 目录: &Path) -> std::io::Result<()> {
+        let 目录 = 目录.join("rime");
+        create_dir_all(&目录)?;
+        let 字母表 = 码表.字母表();
+        let 最大码长 = 码表.最大码长();
+        let 标识 = &码表.标识;
+        let 固态词典名 = format!("{标识}.fixed");
+        let 固态词典 = if 码表.固态词典.is_empty() {
+            None
+        } else {
+            写入固态词典(&目录, &固态词典名, &码表.固态词典)?;
+            Some(固态词典名.as_str())
+        };
+        写入加权词典(&目录, 标识, &码表.条目列表)?;
+        写入方案(&目录, 标识, &码表.名称, &字母表, 最大码长, 固态词典)?;
+        if !码表.繁体条目列表.is_empty() {
+            let 繁体标识 = format!("{标识}_tc");
+            let 繁体名称 = format!("{}・繁", 码表.名称);
+            let 繁体固态词典名 = format!("{繁体标识}.fixed");
+            let 繁体固态词典 = if 码表.繁体固态词典.is_empty() {
+                None
+            } else {
+                写入固态词典(&目录, &繁体固态词典名, &码表.繁体固态词典)?;
+                Some(繁体固态词典名.as_str())
+            };
+            写入加权词典(&目录, &繁体标识, &码表.繁体条目列表)?;
+            写入方案(&目录, &繁体标识, &繁体名称, &字母表, 最大码长, 繁体固态词典)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use crate::chart::键盘图;
+use crate::common::{冰雪命令行参数, 方案, 运行方案};
+use crate::feihua::encoder::冰雪飞花编码器;
+use crate::feihua::objective::冰雪飞花目标函数;
+use crate::feihua::operators::冰雪飞花操作;
+use crate::feihua::{冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花选项};
+use chai::config::配置;
+use chai::contexts::上下文;
+use chai::interfaces::command_line::{命令行, 默认命令行参数};
+use chai::interfaces::默认输入;
+use chai::错误;
+use std::fs::write;
+use std::path::PathBuf;
+
+pub struct 冰雪飞花方案 {
+    pub 上下文: 冰雪飞花上下文,
+}
+
+impl 方案 for 冰雪飞花方案 {
+    type 决策 = 冰雪飞花决策;
+    type 上下文 = 冰雪飞花上下文;
+    type 编码器 = 冰雪飞花编码器;
+    type 目标函数 = 冰雪飞花目标函数;
+    type 操作 = 冰雪飞花操作;
+
+    const 默认导出器: &'static [&'static str] = 冰雪飞花上下文::默认导出器;
+
+    fn 新建(
+        命令行参数: &冰雪命令行参数, 输入: 默认输入
+    ) -> Result<Self, 错误> {
+        let 选项 = 冰雪飞花选项::读取(命令行参数.options.as_ref())?;
+        Ok(Self {
+            上下文: 冰雪飞花上下文::新建(&输入, 选项)?,
+        })
+    }
+
+    fn 上下文(&self) -> &冰雪飞花上下文 {
+        &self.上下文
+    }
+
+    fn 配置(&self) -> &配置 {
+        &self.上下文.配置
+    }
+
+    fn 初始决策(&self) -> &冰雪飞花决策 {
+        &self.上下文.初始决策
+    }
+
+    fn 新建编码器(&self, _: bool) -> Result<冰雪飞花编码器, 错误> {
+        Ok(冰雪飞花编码器::新建(&self.上下文))
+    }
+
+    fn 新建目标函数(
+        &self,
+        编码器: 冰雪飞花编码器,
+    ) -> Result<冰雪飞花目标函数, 错误> {
+        Ok(冰雪飞花目标函数::新建(&self.上下文, 编码器))
+    }
+
+    fn 新建操作(
+        &self, _: &mut 冰雪飞花目标函数, 种子: u64, _: &命令行
+    ) -> 冰雪飞花操作 {
+        冰雪飞花操作::新建(&self.上下文, 种子)
+    }
+
+    fn 输出码表(
+        &self,
+        目标函数: &冰雪飞花目标函数,
+        输出目录: &PathBuf,
+        导出器列表: &[String],
+    ) -> Result<(), 错误> {
+        let 编码结果 = &目标函数.编码器.编码结果;
+        let 码表 = self.上下文.生成码表(编码结果);
+        let 拆分表 = self.上下文.生成拆分表(&目标函数.编码器);
+        self.上下文
+            .输出码表(输出目录, 编码结果, &码表, &拆分表, 导出器列表)
+    }
+
+    fn 分析码表(
+        &self, 目标函数: &冰雪飞花目标函数, 输出目录: &PathBuf
+    ) -> Result<(), 错误> {
+        let 编码结果 = &目标函数.编码器.编码结果;
+        let 码表 = self.上下文.生成码表(编码结果);
+        self.上下文
+            .分析码表(编码结果, &码表, &输出目录.join("分析.md"))
+    }
+
+    fn 生成键盘图(&self, 决策: &冰雪飞花决策) -> 键盘图 {
+        self.上下文.生成键盘图(决策)
+    }
+
+    // 和优化一样写到带时间戳的输出目录，多个实验可以同时运行
+    fn 编码输出目录(&self, 参数: 默认命令行参数) -> Result<PathBuf, 错误> {
+        Ok(命令行::新建(参数, None).输出目录)
+    }
+
+    // 部首选择不在方案配置中，单独写出，可以作为选项中的部首选择
+    fn 写出决策(
+        &self,
+        决策: &冰雪飞花决策,
+        输出目录: &PathBuf,
+        优化后: bool,
+    ) -> Result<(), 错误> {
+        if !优化后 {
+            write(输出目录.join("initial.yaml"), self.上下文.序列化(决策))?;
+        }
+        write(
+            输出目录.join("部首选择.yaml"),
+            self.上下文.序列化部首选择(决策),
+        )?;
+        Ok(())
+    }
+}
+
+/// 运行冰雪飞花，`参数` 的第一项是程序名
+pub fn 运行(参数: Vec<String>) -> Result<(), 错误> {
+    运行方案::<冰雪飞花方案>(参数)
+}
@@ -1,11 +1,12 @@
 use crate::{
-    common::转换,
+    common::{关联序号, 展开归并, 构建关联映射, 转换},
     feihua::{
-        冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花汉字信息, 动态拆分项, 小, 线性化决策, 编码
+        全, 冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花决策变化, 冰雪飞花多音字选项, 冰雪飞花安排,
+        冰雪飞花汉字信息, 冰雪飞花简码选项, 动态拆分项, 大, 大集合, 小, 空格, 线性化决策, 编码, 键,
     },
 };
 use chai::{encoders::编码器, 元素, 棱镜, 编码信息};
-use std::iter::zip;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 pub struct 冰雪飞花编码信息 {
     pub 全码: 编码,
@@ -14,6 +15,56 @@ pub struct 冰雪飞花编码信息 {
     pub 指数频率: f64,
     pub 选重: bool,
     pub 候选位置: u8,
+    /// 全码和同一个字频率更高的另一个读音相同
+    pub 同字重码: bool,
+    /// 同字重码不计入选重时，全码相同的那个读音；两者在码表中是同一个条目，简码也相同
+    pub 并入读音: Option<usize>,
+    /// 全码超出了编码空间，不参与重码和简码的统计
+    pub 不可编码: bool,
+}
+
+const 最大备选长度: usize = 12;
+
+/// 声母和第二码相同、还没有简码的字，按频率排列
+#[derive(Debug, Clone, Copy, Default)]
+pub struct 简码队列 {
+    pub 数据: [(usize, u64); 最大备选长度],
+    pub 当前索引: usize,
+    pub 长度: usize,
+}
+
+impl 简码队列 {
+    fn 入队(&mut self, 序号: usize, 频率: u64) {
+        if self.长度 < 最大备选长度 {
+            self.数据[self.长度] = (序号, 频率);
+            self.长度 += 1;
+        }
+    }
+
+    fn 队首(&self) -> Option<usize> {
+        (self.当前索引 < self.长度).then(|| self.数据[self.当前索引].0)
+    }
+
+    /// 队首出简之后，下一个字还能得到二简，所以两者的频率都计入
+    fn 频率(&self, 二简: bool) -> u64 {
+        let 频率 = |索引: usize| {
+            if 索引 < self.长度 {
+                self.数据[索引].1
+            } else {
+                0
+            }
+        };
+        if 二简 {
+            频率(self.当前索引) + 频率(self.当前索引 + 1)
+        } else {
+            频率(self.当前索引)
+        }
+    }
+
+    fn 重置(&mut self) {
+        self.当前索引 = 0;
+        self.长度 = 0;
+    }
 }
 
 pub struct 冰雪飞花编码器 {
@@ -23,6 +74,31 @@ pub struct 冰雪飞花编码器 {
     pub 棱镜: 棱镜,
     pub 编码结果: Vec<冰雪飞花编码信息>,
     pub 编码空间: Vec<u8>,
+    /// 每个字块当前采用的拆分方式
+    pub 当前拆分: Vec<[元素; 3]>,
+    /// 元素到拆分方式中含有它的字块的序号
+    pub 块关联映射: FxHashMap<元素, Vec<usize>>,
+    /// 元素到声母、候选部首或者字块的某个拆分方式中含有它的字的序号
+    pub 字关联映射: FxHashMap<元素, Vec<usize>>,
+    /// 每个字当前选用的部首，0 表示没有部首
+    pub 当前部首: Vec<元素>,
+    /// 每个部首组的候选部首和组内的字
+    pub 部首组候选: Vec<Vec<元素>>,
+    pub 部首组字: Vec<Vec<usize>>,
+    /// 是否已经完整编码过一次，之后才能按变化增量更新
+    已编码: bool,
+    /// 没有任何一种拆分方式的字根都在小集合上的字块
+    pub 不可行块: FxHashSet<usize>,
+    /// 同一个字频率更高的其他读音的序号
+    pub 同字前序: Vec<Vec<usize>>,
+    pub 多音字选项: 冰雪飞花多音字选项,
+    pub 简码选项: 冰雪飞花简码选项,
+    /// 每个声母键下可用的一简，按当量从小到大排列
+    pub 一简候选: Vec<Vec<编码>>,
+    /// 每个声母键下按第二码分的队列
+    pub 简码队列: Vec<[简码队列; 全]>,
+    /// 三简是否已被占用，按前三码索引
+    pub 三简空间: Vec<bool>,
 }
 
 impl 冰雪飞花编码器 {
@@ -38,8 +114,36 @@ impl 冰雪飞花编码器 {
                 指数频率: ((索引.min(6000) as f64) / -2000.0).exp(),
                 选重: false,
                 候选位置: 0,
+                同字重码: false,
+                并入读音: None,
+                不可编码: false,
             })
             .collect();
+        let mut 同字前序: Vec<Vec<usize>> = vec![vec![]; 上下文.信息列表.len()];
+        let mut 读音序号: FxHashMap<char, Vec<usize>> = FxHashMap::default();
+        for (序号, 信息) in 上下文.信息列表.iter().enumerate() {
+            let 列表 = 读音序号.entry(信息.汉字).or_default();
+            同字前序[序号] = 列表.clone();
+            列表.push(序号);
+        }
+        let 块关联映射 =
+            构建关联映射(上下文.动态拆分.iter().map(|x| x.iter().flatten().cloned()));
+        let mut 部首组字 = vec![vec![]; 上下文.部首组列表.len()];
+        for (序号, 信息) in 上下文.信息列表.iter().enumerate() {
+            if 信息.部首组 != usize::MAX {
+                部首组字[信息.部首组].push(序号);
+            }
+        }
+        let 字关联映射 = 构建关联映射(上下文.信息列表.iter().map(|信息| {
+            let mut 元素列表 = vec![信息.声母, 信息.部首];
+            if 信息.部首组 != usize::MAX {
+                元素列表.extend(&上下文.部首组列表[信息.部首组].候选);
+            }
+            for 块 in 信息.字块.iter().filter(|x| **x != usize::MAX) {
+                元素列表.extend(上下文.动态拆分[*块].iter().flatten());
+            }
+            元素列表
+        }));
         Self {
             汉字信息: 上下文.信息列表.clone(),
             动态拆分: 上下文.动态拆分.clone(),
@@ -51,57 +155,170 @@ impl 冰雪飞花编码器 {
             棱镜: 上下文.棱镜.clone(),
             编码空间: vec![0; 编码::编码空间大小()],
             编码结果,
+            当前拆分: vec![Default::default(); 上下文.动态拆分.len()],
+            块关联映射,
+            字关联映射,
+            当前部首: 上下文.信息列表.iter().map(|x| x.部首).collect(),
+            部首组候选: 上下文.部首组列表.iter().map(|x| x.候选.clone()).collect(),
+            部首组字,
+            已编码: false,
+            不可行块: FxHashSet::default(),
+            同字前序,
+            多音字选项: 上下文.多音字选项.clone(),
+            简码选项: 上下文.简码选项.clone(),
+            一简候选: Self::生成一简候选(上下文),
+            简码队列: vec![Default::default(); 大],
+            三简空间: vec![false; 大 * 全 * (小 + 1)],
         }
     }
 }
 
 impl 冰雪飞花编码器 {
-    pub fn 刷新拆分序列(&mut self, 决策: &线性化决策, _变化: &Option<()>) {
-        let mut 当前拆分索引 = vec![Default::default(); self.动态拆分.len()];
-        for (指针, 拆分方式列表) in zip(&mut 当前拆分索引, &self.动态拆分) {
-            let mut found = false;
-            for 拆分方式 in 拆分方式列表.iter() {
-                // 找到一个所有字根都在小集合内的拆分方式
-                if 拆分方式.iter().all(|&x| {
+    fn 生成一简候选(上下文: &冰雪飞花上下文) -> Vec<Vec<编码>> {
+        let 棱镜 = &上下文.棱镜;
+        let 当量 = |声母: char, 键: char| {
+            let 组合: String = [声母, 键].iter().collect();
+            上下文.原始当量信息.get(&组合).copied().unwrap_or(0.0)
+        };
+        大集合
+            .iter()
+            .map(|声母| {
+                let mut 键列表: Vec<_> = 上下文.简码选项.一简键.chars().collect();
+                键列表.sort_by(|a, b| 当量(*声母, *a).partial_cmp(&当量(*声母, *b)).unwrap());
+                键列表
+                    .into_iter()
+                    .map(|x| 编码([棱镜.键转数字[声母] as 键, 棱镜.键转数字[&x] as 键, 0, 0]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// 变化涉及的所有元素，连同直接或间接归并到它们的元素
+    fn 展开变化(
+        决策: &冰雪飞花决策, 变化: &冰雪飞花决策变化
+    ) -> FxHashSet<元素> {
+        let mut 元素集合: FxHashSet<_> = 变化.字根.iter().cloned().collect();
+        let 归并关系 = 决策
+            .元素
+            .iter()
+            .enumerate()
+            .filter_map(|(元素, 安排)| match 安排 {
+                冰雪飞花安排::归并(x) => Some((元素, *x)),
+                _ => None,
+            });
+        展开归并(&mut 元素集合, 归并关系);
+        元素集合
+    }
+
+    /// 找到一个所有字根都在小集合内的拆分方式
+    fn 选择拆分(&self, 块: usize, 决策: &线性化决策) -> Option<[元素; 3]> {
+        self.动态拆分[块]
+            .iter()
+            .find(|拆分方式| {
+                拆分方式.iter().all(|&x| {
                     if x == 0 {
                         return true;
                     }
                     let c = 决策[x] as usize;
                     c <= 小 && c != 0
-                }) {
-                    *指针 = *拆分方式;
-                    found = true;
-                    break;
+                })
+            })
+            .cloned()
+    }
+
+    /// 列出字块的所有拆分方式，用于报告无法编码的字块
+    pub fn 描述字块(&self, 块: usize) -> String {
+        let 拆分方式列表: Vec<String> = self.动态拆分[块]
+            .iter()
+            .map(|x| {
+                x.iter()
+                    .filter(|y| **y != 0)
+                    .map(|y| self.棱镜.数字转元素[y].clone())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        format!("[{}]", 拆分方式列表.join(" | "))
+    }
+
+    /// 按决策更新部首组内的字选用的部首，返回部首改变了的字；没有给出部首组时全部更新
+    pub fn 刷新部首(
+        &mut self,
+        决策: &冰雪飞花决策,
+        部首组列表: Option<&[usize]>,
+    ) -> Vec<usize> {
+        let 部首组列表: Vec<_> = match 部首组列表 {
+            Some(列表) => 列表.to_vec(),
+            None => (0..self.部首组字.len()).collect(),
+        };
+        let mut 字列表 = vec![];
+        for 组 in 部首组列表 {
+            let 部首 = self.部首组候选[组][决策.部首[组]];
+            for 序号 in &self.部首组字[组] {
+                if self.当前部首[*序号] != 部首 {
+                    self.当前部首[*序号] = 部首;
+                    字列表.push(*序号);
                 }
             }
-            if !found {
-                panic!(
-                    "无法为拆分项 {:?} 找到合适的拆分方式",
-                    拆分方式列表
-                        .iter()
-                        .map(|x| x.map(|y| if y == 0 {
-                            "".into()
-                        } else {
-                            self.棱镜.数字转元素[&y].clone()
-                        }))
-                        .collect::<Vec<_>>()
-                );
-            }
-        }
-        for (输出, 信息) in zip(&mut self.拆分序列, &self.汉字信息) {
-            let 拆分 = if 信息.字块[1] == usize::MAX {
-                当前拆分索引[信息.字块[0]]
-            } else {
-                let 拆分一 = 当前拆分索引[信息.字块[0]];
-                let 拆分二 = 当前拆分索引[信息.字块[1]];
-                [拆分一[0], 拆分二[0], 拆分二[1]]
-            };
-            if 信息.部首 == 0 {
-                (输出[1], 输出[2], 输出[3]) = (拆分[0], 拆分[1], 拆分[2]);
-            } else {
-                (输出[2], 输出[3]) = (拆分[0], 拆分[1]);
+        }
+        字列表
+    }
+
+    fn 刷新字拆分序列(&mut self, 序号: usize) {
+        let 信息 = &self.汉字信息[序号];
+        let 部首 = self.当前部首[序号];
+        let 输出 = &mut self.拆分序列[序号];
+        let 拆分 = if 信息.字块[1] == usize::MAX {
+            self.当前拆分[信息.字块[0]]
+        } else {
+            let 拆分一 = self.当前拆分[信息.字块[0]];
+            let 拆分二 = self.当前拆分[信息.字块[1]];
+            [拆分一[0], 拆分二[0], 拆分二[1]]
+        };
+        if 部首 == 0 {
+            (输出[1], 输出[2], 输出[3]) = (拆分[0], 拆分[1], 拆分[2]);
+        } else {
+            (输出[1], 输出[2], 输出[3]) = (部首, 拆分[0], 拆分[1]);
+        }
+    }
+
+    /// 刷新受影响的字块和字的拆分序列，返回需要重新生成全码的字；没有给出元素集合时全部刷新。
+    /// 部首改变了的字也一并刷新。
+    pub fn 刷新拆分序列(
+        &mut self,
+        决策: &线性化决策,
+        元素集合: Option<&FxHashSet<元素>>,
+        部首变化字: &[usize],
+    ) -> Vec<usize> {
+        let (块列表, 字列表) = match 元素集合 {
+            Some(元素集合) => {
+                let mut 字列表 = 关联序号(&self.字关联映射, 元素集合);
+                字列表.extend(部首变化字);
+                字列表.sort_unstable();
+                字列表.dedup();
+                (关联序号(&self.块关联映射, 元素集合), 字列表)
             }
+            None => (
+                (0..self.动态拆分.len()).collect(),
+                (0..self.汉字信息.len()).collect(),
+            ),
+        };
+        // 没有可行的拆分方式时保留原来的拆分，记为不可行，由目标函数惩罚
+        for 块 in 块列表 {
+            match self.选择拆分(块, 决策) {
+                Some(拆分) => {
+                    self.当前拆分[块] = 拆分;
+                    self.不可行块.remove(&块);
+                }
+                None => {
+                    self.不可行块.insert(块);
+                }
+            }
+        }
+        for 序号 in &字列表 {
+            self.刷新字拆分序列(*序号);
         }
+        字列表
     }
 
     pub fn 重置空间(&mut self) {
@@ -120,15 +337,39 @@ impl 冰雪飞花编码器 {
         ])
     }
 
-    pub fn 生成全码(&mut self, 决策: &线性化决策) {
-        for (输出, 序列) in zip(&mut self.编码结果, &self.拆分序列) {
-            输出.全码 = Self::全码规则(序列, 决策);
-            let hash = 输出.全码.hash();
-            if hash >= self.编码空间.len() {
-                panic!(
-                    "编码：{:?} 超出编码空间范围，当前序列：{:?}",
-                    输出.全码, 序列
-                );
+    pub fn 生成全码(&mut self, 决策: &线性化决策, 字列表: &[usize]) {
+        for 序号 in 字列表 {
+            self.编码结果[*序号].全码 = Self::全码规则(&self.拆分序列[*序号], 决策);
+        }
+    }
+
+    /// 按频率顺序把全码放入编码空间，统计每个字的候选位置
+    pub fn 统计重码(&mut self) {
+        for 序号 in 0..self.编码结果.len() {
+            let 全码 = self.编码结果[序号].全码;
+            if !全码.可编码() {
+                let 输出 = &mut self.编码结果[序号];
+                (输出.不可编码, 输出.同字重码, 输出.选重) = (true, false, false);
+                输出.并入读音 = None;
+                continue;
+            }
+            self.编码结果[序号].不可编码 = false;
+            let hash = 全码.hash();
+            let 同字读音 = self.同字前序[序号]
+                .iter()
+                .find(|x| self.编码结果[**x].全码 == 全码)
+                .map(|x| (*x, self.编码结果[*x].选重, self.编码结果[*x].候选位置));
+            let 输出 = &mut self.编码结果[序号];
+            输出.同字重码 = 同字读音.is_some();
+            输出.并入读音 = None;
+            // 不计入选重时，同码的读音在码表中只是同一个条目
+            if let Some((读音, 选重, 候选位置)) = 同字读音 {
+                if !self.多音字选项.同字重码计入选重 {
+                    输出.并入读音 = Some(读音);
+                    输出.选重 = 选重;
+                    输出.候选位置 = 候选位置;
+                    continue;
+                }
             }
             输出.选重 = self.编码空间[hash] > 0;
             输出.候选位置 = self.编码空间[hash];
@@ -136,21 +377,94 @@ impl 冰雪飞花编码器 {
         }
     }
 
-    // pub fn 生成简码(&mut self) {
-    //     for 输出 in &mut self.编码结果 {
-    //         输出.简码 = 输出.全码;
-    //     }
-    // }
+    /// 依次分配一简、二简和三简，没有简码的字简码与全码相同
+    pub fn 生成简码(&mut self) {
+        const 声母位移: usize = 小 + 1;
+        for 输出 in &mut self.编码结果 {
+            输出.简码 = 输出.全码;
+        }
+        for 队列列表 in &mut self.简码队列 {
+            队列列表.iter_mut().for_each(|x| x.重置());
+        }
+        let 出简字数 = self.简码选项.出简字数.min(self.编码结果.len());
+        for (序号, 输出) in self.编码结果[..出简字数].iter().enumerate() {
+            // 两码的全码已经足够短，并入其他读音的不单独出简
+            let [声母, 第二码, 第三码, _] = 输出.全码.0;
+            if 第三码 == 0 || 输出.并入读音.is_some() || 输出.不可编码 {
+                continue;
+            }
+            self.简码队列[声母 as usize - 声母位移][第二码 as usize - 1].入队(序号, 输出.频率);
+        }
+        let 二简 = self.简码选项.二简;
+        for (声母序号, 队列列表) in self.简码队列.iter_mut().enumerate() {
+            for 一简 in &self.一简候选[声母序号] {
+                if self.编码空间[一简.hash()] > 0 {
+                    continue;
+                }
+                let 队列 = 队列列表.iter_mut().max_by_key(|x| x.频率(二简)).unwrap();
+                let Some(序号) = 队列.队首() else {
+                    break;
+                };
+                队列.当前索引 += 1;
+                self.编码结果[序号].简码 = *一简;
+            }
+            if !二简 {
+                continue;
+            }
+            for 队列 in 队列列表.iter() {
+                if let Some(序号) = 队列.队首() {
+                    let [声母, 第二码, ..] = self.编码结果[序号].全码.0;
+                    self.编码结果[序号].简码 = 编码([声母, 第二码, 空格, 0]);
+                }
+            }
+        }
+        self.三简空间.iter_mut().for_each(|x| *x = false);
+        let 三简字数 = self.简码选项.三简字数.min(self.编码结果.len());
+        for 输出 in &mut self.编码结果[..三简字数] {
+            let [声母, 第二码, 第三码, 第四码] = 输出.全码.0;
+            if 输出.简码 != 输出.全码 || 第四码 == 0 || 输出.并入读音.is_some() || 输出.不可编码
+            {
+                continue;
+            }
+            let 索引 = ((声母 as usize - 声母位移) * 全 + 第二码 as usize - 1) * (小 + 1)
+                + 第三码 as usize;
+            if !self.三简空间[索引] {
+                self.三简空间[索引] = true;
+                输出.简码 = 编码([声母, 第二码, 第三码, 空格]);
+            }
+        }
+        // 并入的读音排在它并入的读音之后，按顺序复制即可
+        for 序号 in 0..self.编码结果.len() {
+            if let Some(读音) = self.编码结果[序号].并入读音 {
+                self.编码结果[序号].简码 = self.编码结果[读音].简码;
+            }
+        }
+    }
 }
 
 impl 编码器 for 冰雪飞花编码器 {
     type 决策 = 冰雪飞花决策;
 
-    fn 编码(&mut self, 决策: &冰雪飞花决策, 变化: &Option<()>, _: &mut [编码信息]) {
+    fn 编码(
+        &mut self, 决策: &冰雪飞花决策, 变化: &Option<冰雪飞花决策变化>, _: &mut [编码信息]
+    ) {
+        let 线性化 = 决策.线性化(&self.棱镜);
+        // 只刷新受影响的字块和字，重码仍然整体统计
+        let 字列表 = match 变化 {
+            Some(变化) if self.已编码 => {
+                let 元素集合 = Self::展开变化(决策, 变化);
+                let 部首变化字 = self.刷新部首(决策, Some(&变化.部首组));
+                self.刷新拆分序列(&线性化, Some(&元素集合), &部首变化字)
+            }
+            _ => {
+                self.已编码 = true;
+                self.刷新部首(决策, None);
+                self.刷新拆分序列(&线性化, None, &[])
+            }
+        };
+        self.生成全码(&线性化, &字列表);
         self.重置空间();
-        let 决策 = 决策.线性化(&self.棱镜);
-        self.刷新拆分序列(&决策, 变化);
-        self.生成全码(&决策);
-        // self.生成简码();
+        self.统计重码();
+        self.生成简码();
     }
 }
@@ -1,29 +1,33 @@
+pub mod cli;
 pub mod encoder;
 pub mod objective;
 pub mod operators;
 use crate::{
-    common::{get_pua_mapper, 转换},
+    chart::{标注类别, 键盘图},
+    common::{get_pua_mapper, 解析文件, 读取选项, 转换},
+    exporters::{导出, 导出条目, 导出码表, 条目类型},
     feihua::encoder::{冰雪飞花编码信息, 冰雪飞花编码器},
     qingyun::context::写入文本文件,
 };
 use chai::{
     config::{Mapped, MappedKey, 配置},
     contexts::{上下文, 合并初始决策, 展开变量, 拓扑排序, 条件, 条件安排},
+    encoders::编码器,
     interfaces::默认输入,
     objectives::metric::指法标记,
     optimizers::决策,
-    元素, 棱镜, 码表项, 错误,
+    元素, 原始当量信息, 原始键位分布信息, 棱镜, 码表项, 错误,
 };
 use chrono::Local;
 use indexmap::IndexMap;
 use rustc_hash::FxHashMap;
 use serde::Deserialize;
-use serde_yaml::{from_str, to_string};
+use serde_yaml::to_string;
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
 use std::{
-    fs::{File, read_to_string},
-    path::PathBuf,
+    io::{Error, ErrorKind, Write},
+    iter::zip,
 };
-use std::{io::Write, iter::zip};
 
 pub const 大: usize = 20;
 pub const 小: usize = 8;
@@ -38,7 +42,7 @@ pub const 全集合: [char; 全] = [
     'y', 'a', 'o', 'e', 'i', 'u', 'v', ';', '/', ',', '.',
 ];
 pub type 键 = u8;
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct 编码([键; 4]);
 pub const 空格: 键 = 31;
 
@@ -56,6 +60,14 @@ impl 转换 for 编码 {
     }
 }
 
+impl 编码 {
+    /// 声母在大集合上、第二码不为空、形码都在小集合上，才能放进编码空间
+    pub fn 可编码(&self) -> bool {
+        let [c1, c2, c3, c4] = self.0.map(|x| x as usize);
+        小 < c1 && c1 <= 小 + 大 && 0 < c2 && c2 <= 全 && c3 <= 小 && c4 <= 小
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum 冰雪飞花安排 {
     键位(键),
@@ -87,13 +99,45 @@ impl 冰雪飞花安排 {
 #[derive(Clone, Debug)]
 pub struct 冰雪飞花决策 {
     pub 元素: Vec<冰雪飞花安排>,
+    /// 每个部首组选用的候选部首的序号
+    pub 部首: Vec<usize>,
+}
+
+/// 一次变异中改变了安排的元素和改变了部首的部首组
+#[derive(Clone, Debug, Default)]
+pub struct 冰雪飞花决策变化 {
+    pub 字根: Vec<元素>,
+    pub 部首组: Vec<usize>,
+}
+
+impl 冰雪飞花决策变化 {
+    pub fn 无变化() -> Self {
+        Self::default()
+    }
+
+    pub fn 是无变化(&self) -> bool {
+        self.字根.is_empty() && self.部首组.is_empty()
+    }
 }
 
 impl 决策 for 冰雪飞花决策 {
-    type 变化 = ();
+    type 变化 = 冰雪飞花决策变化;
 
-    fn 除法(_旧变化: &Self::变化, _新变化: &Self::变化) -> Self::变化 {
-        ()
+    // 被拒绝的变异和新的变异涉及的元素都需要重新编码
+    fn 除法(旧变化: &Self::变化, 新变化: &Self::变化) -> Self::变化 {
+        let mut 字根 = 旧变化.字根.clone();
+        for x in &新变化.字根 {
+            if !字根.contains(x) {
+                字根.push(*x);
+            }
+        }
+        let mut 部首组 = 旧变化.部首组.clone();
+        for x in &新变化.部首组 {
+            if !部首组.contains(x) {
+                部首组.push(*x);
+            }
+        }
+        冰雪飞花决策变化 { 字根, 部首组 }
     }
 }
 
@@ -131,11 +175,144 @@ pub struct 冰雪飞花汉字信息 {
     pub 声母: 元素,
     // 0 表示没有部首
     pub 部首: 元素,
+    /// 有候选部首时所属的部首组，没有时为 usize::MAX
+    pub 部首组: usize,
     pub 字块: [usize; 2],
 }
 
+/// 数据中的部首和候选部首都相同的字组成一个部首组，组内的字总是选用同一个部首
+#[derive(Clone, Debug)]
+pub struct 冰雪飞花部首组 {
+    /// 候选部首以 / 连接，没有部首记为「无」
+    pub 名称: String,
+    /// 第一个是数据中的部首，0 表示没有部首
+    pub 候选: Vec<元素>,
+}
+
+impl 冰雪飞花部首组 {
+    /// 按每个字的候选部首（数据中的部首在最前）划分部首组，返回部首组和每个字所属的部首组
+    ///
+    /// 只有一个候选的字不属于任何部首组，记为 usize::MAX；这样每个字只会选用它自己列出的部首
+    pub fn 划分(
+        候选列表: &[Vec<元素>],
+        名称: impl Fn(元素) -> String,
+    ) -> (Vec<Self>, Vec<usize>) {
+        let mut 部首组列表: Vec<Self> = vec![];
+        let mut 部首组序号: FxHashMap<&[元素], usize> = FxHashMap::default();
+        let 所属部首组 = 候选列表
+            .iter()
+            .map(|候选| {
+                if 候选.len() == 1 {
+                    return usize::MAX;
+                }
+                *部首组序号.entry(候选.as_slice()).or_insert_with(|| {
+                    let 名称: Vec<_> = 候选.iter().map(|x| 名称(*x)).collect();
+                    部首组列表.push(Self {
+                        名称: 名称.join("/"),
+                        候选: 候选.clone(),
+                    });
+                    部首组列表.len() - 1
+                })
+            })
+            .collect();
+        (部首组列表, 所属部首组)
+    }
+}
+
 pub type 动态拆分项 = Vec<[usize; 3]>;
 
+/// 冰雪飞花的选项，默认从 `feihua/options.yaml` 读取，也可以用 `--options` 指定
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪飞花选项 {
+    /// 汉字信息和动态拆分文件，相对于当前目录
+    pub 拆分文件: PathBuf,
+    pub 简码: 冰雪飞花简码选项,
+    pub 权重: 冰雪飞花权重,
+    pub 多音字: 冰雪飞花多音字选项,
+    /// 部首组名称到初始选用的部首，没有列出的组选用数据中的部首
+    pub 部首选择: BTreeMap<String, String>,
+}
+
+/// 多音字的不同声母各是一个编码对象，两个读音的全码相同时称为同字重码
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct 冰雪飞花多音字选项 {
+    /// 同字重码是否也像不同字的重码一样占据一个候选位置并计入选重
+    pub 同字重码计入选重: bool,
+}
+
+impl Default for 冰雪飞花选项 {
+    fn default() -> Self {
+        Self {
+            拆分文件: "feihua/dynamic_analysis.yaml".into(),
+            简码: Default::default(),
+            权重: Default::default(),
+            多音字: Default::default(),
+            部首选择: Default::default(),
+        }
+    }
+}
+
+impl 冰雪飞花选项 {
+    /// 指定的文件必须存在；没有指定时默认文件可以不存在，此时全部使用默认值
+    pub fn 读取(路径: Option<&PathBuf>) -> Result<Self, 错误> {
+        读取选项(路径, "feihua/options.yaml")
+    }
+}
+
+/// 简码按频率分配：每个声母下的一简组合依次给当前频率最高的「声母 + 第二码」队列的队首，
+/// 队列中剩下的第一个字得到这个组合加空格的二简。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪飞花简码选项 {
+    /// 一简的第二码，和声母组合的当量小的先分配；已经被全码占用的组合不出一简
+    pub 一简键: String,
+    /// 频率最高的多少个字参与一简和二简
+    pub 出简字数: usize,
+    /// 是否输出以空格结尾的二简
+    pub 二简: bool,
+    /// 频率最高的多少个字可以用前三码加空格作为三简，0 表示不出三简
+    pub 三简字数: usize,
+}
+
+impl Default for 冰雪飞花简码选项 {
+    fn default() -> Self {
+        Self {
+            一简键: "aoeiuv;/".to_string(),
+            出简字数: 3000,
+            二简: true,
+            三简字数: 1500,
+        }
+    }
+}
+
+/// 各项指标在分数中的权重，手感指标都按简码计算
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪飞花权重 {
+    /// 按指数频率加权的全码选重率
+    pub 选重率: f64,
+    pub 码长: f64,
+    pub 组合当量: f64,
+    pub 按键分布偏差: f64,
+    pub 同指大跨排: f64,
+    pub 错手: f64,
+}
+
+impl Default for 冰雪飞花权重 {
+    fn default() -> Self {
+        Self {
+            选重率: 1.0,
+            码长: 0.01,
+            组合当量: 0.05,
+            按键分布偏差: 0.015,
+            同指大跨排: 0.0,
+            错手: 0.0,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct 冰雪飞花上下文 {
     pub 配置: 配置,
@@ -144,6 +321,12 @@ pub struct 冰雪飞花上下文 {
     pub 棱镜: 棱镜,
     pub 信息列表: Vec<冰雪飞花汉字信息>,
     pub 动态拆分: Vec<动态拆分项>,
+    pub 部首组列表: Vec<冰雪飞花部首组>,
+    pub 简码选项: 冰雪飞花简码选项,
+    pub 权重: 冰雪飞花权重,
+    pub 多音字选项: 冰雪飞花多音字选项,
+    pub 原始键位分布信息: 原始键位分布信息,
+    pub 原始当量信息: 原始当量信息,
 }
 
 impl 上下文 for 冰雪飞花上下文 {
@@ -193,6 +376,9 @@ struct 原始汉字信息项 {
     pub 汉字: char,
     pub 读音: Vec<原始读音>,
     pub 部首: Option<String>,
+    /// 除了部首之外还可以选用的部首，「无」表示可以不用部首
+    #[serde(default)]
+    pub 候选部首: Vec<String>,
     pub 字块: Vec<String>,
 }
 
@@ -200,14 +386,8 @@ type 原始汉字信息 = Vec<原始汉字信息项>;
 type 原始动态拆分 = FxHashMap<String, Vec<Vec<String>>>;
 
 impl 冰雪飞花上下文 {
-    pub fn 新建(输入: &默认输入) -> Self {
-        let 布局 = 输入.配置.form.clone();
-        let 原始决策 = 布局.mapping;
-        let mut 原始决策空间 = 布局.mapping_space.unwrap_or_default();
-        let 原始变量映射 = 布局.mapping_variables.unwrap_or_default();
-        合并初始决策(&mut 原始决策空间, &原始决策);
-        展开变量(&mut 原始决策空间, &原始变量映射);
-        let (所有元素, 元素图) = 拓扑排序(&原始决策空间).unwrap();
+    /// 键按小集合、大集合、逗号、句号、空格编号，元素接在键之后
+    pub fn 生成棱镜(所有元素: &[String]) -> 棱镜 {
         let mut 元素转数字 = FxHashMap::default();
         let mut 数字转元素 = FxHashMap::default();
         let mut 键转数字 = FxHashMap::default();
@@ -224,20 +404,33 @@ impl 冰雪飞花上下文 {
             键转数字.insert(键, 序号 as u64);
             数字转键.insert(序号 as u64, 键);
         }
-        for 元素名称 in &所有元素 {
+        for 元素名称 in 所有元素 {
             序号 += 1;
             元素转数字.insert(元素名称.clone(), 序号);
             数字转元素.insert(序号, 元素名称.clone());
         }
-        let 棱镜 = 棱镜 {
+        棱镜 {
             进制: 32 as u64,
             元素转数字,
             数字转元素,
             键转数字,
             数字转键,
-        };
+        }
+    }
+
+    /// 初始决策无法编码时返回错误，列出所有问题
+    pub fn 新建(输入: &默认输入, 选项: 冰雪飞花选项) -> Result<Self, 错误> {
+        let 布局 = 输入.配置.form.clone();
+        let 原始决策 = 布局.mapping;
+        let mut 原始决策空间 = 布局.mapping_space.unwrap_or_default();
+        let 原始变量映射 = 布局.mapping_variables.unwrap_or_default();
+        合并初始决策(&mut 原始决策空间, &原始决策);
+        展开变量(&mut 原始决策空间, &原始变量映射);
+        let (所有元素, 元素图) = 拓扑排序(&原始决策空间).unwrap();
+        let 棱镜 = Self::生成棱镜(&所有元素);
         let mut 初始决策 = 冰雪飞花决策 {
             元素: vec![冰雪飞花安排::键位(0); 棱镜.元素转数字.len() + 1],
+            部首: vec![],
         };
         for (元素名称, 安排) in &原始决策 {
             let 序号 = 棱镜.元素转数字[元素名称];
@@ -268,8 +461,7 @@ impl 冰雪飞花上下文 {
             }
             决策空间.元素空间[序号] = 条件安排列表;
         }
-        let 拆分输入: 拆分输入 =
-            from_str(&read_to_string("feihua/dynamic_analysis.yaml").unwrap()).unwrap();
+        let 拆分输入: 拆分输入 = 解析文件(&选项.拆分文件)?;
         let mut 动态拆分 = vec![];
         let mut 块转数字 = FxHashMap::default();
         let mut 数字转块 = FxHashMap::default();
@@ -289,17 +481,38 @@ impl 冰雪飞花上下文 {
             动态拆分.push(拆分方式列表);
         }
         let mut 信息列表 = vec![];
-        for 原始信息 in 拆分输入.汉字信息 {
+        let 部首转数字 = |名称: &String| {
+            if 名称 == "无" {
+                0
+            } else {
+                棱镜.元素转数字[名称]
+            }
+        };
+        let 候选列表: Vec<_> = 拆分输入
+            .汉字信息
+            .iter()
+            .map(|原始信息| {
+                let mut 候选 = vec![原始信息.部首.as_ref().map_or(0, 部首转数字)];
+                for 名称 in &原始信息.候选部首 {
+                    let 候选部首 = 部首转数字(名称);
+                    if !候选.contains(&候选部首) {
+                        候选.push(候选部首);
+                    }
+                }
+                候选
+            })
+            .collect();
+        let (部首组列表, 所属部首组) = 冰雪飞花部首组::划分(&候选列表, |x| match x {
+            0 => "无".to_string(),
+            x => 棱镜.数字转元素[&x].clone(),
+        });
+        for (原始信息, 部首组) in zip(拆分输入.汉字信息, 所属部首组) {
             let mut 字块 = [usize::MAX; 2];
             for (索引, 块) in 原始信息.字块.iter().enumerate().take(2) {
                 let 块序号 = 块转数字[块];
                 字块[索引] = 块序号;
             }
-            let 部首 = if let Some(部首名称) = &原始信息.部首 {
-                棱镜.元素转数字[部首名称]
-            } else {
-                0
-            };
+            let 部首 = 原始信息.部首.as_ref().map_or(0, 部首转数字);
             let mut freq = FxHashMap::default();
             for 读音 in &原始信息.读音 {
                 let 声母 = 棱镜.元素转数字[&读音.声];
@@ -313,6 +526,7 @@ impl 冰雪飞花上下文 {
                     频率,
                     声母,
                     部首,
+                    部首组,
                     字块,
                 });
             }
@@ -325,7 +539,8 @@ impl 冰雪飞花上下文 {
             }
             let 元素 = 棱镜.元素转数字[&元素名称];
             let 被依赖 = 元素图[&元素名称].len() > 0;
-            let 是部首 = 信息列表.iter().any(|x| x.部首 == 元素);
+            let 是部首 = 信息列表.iter().any(|x| x.部首 == 元素)
+                || 部首组列表.iter().any(|x| x.候选.contains(&元素));
             if !被依赖 && !是部首 {
                 println!("元素 {:?} 未被依赖且不是部首，可以设为未选取", 元素名称);
                 决策空间.元素空间[元素].push(条件安排 {
@@ -335,14 +550,72 @@ impl 冰雪飞花上下文 {
                 });
             }
         }
-        Self {
+        初始决策.部首 = 部首组列表
+            .iter()
+            .map(|组| match 选项.部首选择.get(&组.名称) {
+                Some(名称) => 组
+                    .候选
+                    .iter()
+                    .position(|x| *x == 部首转数字(名称))
+                    .unwrap_or_else(|| panic!("{名称} 不是部首组 {} 的候选", 组.名称)),
+                None => 0,
+            })
+            .collect();
+        let 上下文 = Self {
             配置: 输入.配置.clone(),
             初始决策,
             决策空间,
             棱镜,
             信息列表,
             动态拆分,
+            部首组列表,
+            简码选项: 选项.简码,
+            权重: 选项.权重,
+            多音字选项: 选项.多音字,
+            原始键位分布信息: 输入.原始键位分布信息.clone(),
+            原始当量信息: 输入.原始当量信息.clone(),
+        };
+        let 问题 = 上下文.检查决策(&上下文.初始决策);
+        if !问题.is_empty() {
+            let 消息 = format!("初始决策无法编码：\n{}", 问题.join("\n"));
+            return Err(Error::new(ErrorKind::InvalidData, 消息).into());
+        }
+        Ok(上下文)
+    }
+
+    /// 检查决策能否编码：每个字块都有可行的拆分方式，每个字的全码都在编码空间内
+    pub fn 检查决策(&self, 决策: &冰雪飞花决策) -> Vec<String> {
+        let mut 编码器 = 冰雪飞花编码器::新建(self);
+        编码器.编码(决策, &None, &mut []);
+        let mut 不可行块: Vec<_> = 编码器.不可行块.iter().cloned().collect();
+        不可行块.sort();
+        let mut 问题: Vec<_> = 不可行块
+            .into_iter()
+            .map(|x| format!("字块 {} 没有字根都在小集合上的拆分方式", 编码器.描述字块(x)))
+            .collect();
+        for (信息, 编码信息) in zip(&编码器.汉字信息, &编码器.编码结果) {
+            if 编码信息.不可编码 {
+                问题.push(format!(
+                    "{} 的全码 {:?} 超出编码空间",
+                    信息.汉字, 编码信息.全码
+                ));
+            }
         }
+        问题
+    }
+
+    /// 各部首组选用的部首，格式和选项中的部首选择相同
+    pub fn 序列化部首选择(&self, 决策: &冰雪飞花决策) -> String {
+        let 部首选择: BTreeMap<_, _> = zip(&self.部首组列表, &决策.部首)
+            .map(|(组, 序号)| {
+                let 部首 = match 组.候选[*序号] {
+                    0 => "无".to_string(),
+                    x => self.棱镜.数字转元素[&x].clone(),
+                };
+                (组.名称.clone(), 部首)
+            })
+            .collect();
+        to_string(&部首选择).unwrap()
     }
 
     pub fn 生成码表(&self, 编码结果: &Vec<冰雪飞花编码信息>) -> Vec<码表项> {
@@ -370,10 +643,10 @@ impl 冰雪飞花上下文 {
     pub fn 生成拆分表(&self, 编码器: &冰雪飞花编码器) -> Vec<(String, String)> {
         let mut 拆分表 = vec![];
         let 映射 = get_pua_mapper();
-        for (拆分序列, 信息) in zip(编码器.拆分序列.iter(), 编码器.汉字信息.iter())
+        for (序号, (拆分序列, 信息)) in zip(&编码器.拆分序列, &编码器.汉字信息).enumerate()
         {
             let mut 序列 = vec![];
-            if 信息.部首 == 0 {
+            if 编码器.当前部首[序号] == 0 {
                 for 字根 in &拆分序列[1..] {
                     if *字根 != 0 {
                         序列.push(self.棱镜.数字转元素[字根].clone());
@@ -404,23 +677,71 @@ impl 冰雪飞花上下文 {
         拆分表
     }
 
+    /// 没有导出配置时生成的文件
+    pub const 默认导出器: &[&str] = &["大竹"];
+
+    pub fn 生成导出码表(
+        &self,
+        编码结果: &[冰雪飞花编码信息],
+        码表: &[码表项],
+        拆分表: &[(String, String)],
+    ) -> 导出码表 {
+        let mut 条目列表 = vec![];
+        for (码表项, 编码信息) in zip(码表, 编码结果) {
+            // 并入了同一个字的另一个读音，已经导出过
+            if 编码信息.并入读音.is_some() {
+                continue;
+            }
+            条目列表.push(导出条目 {
+                词: 码表项.name.clone(),
+                编码: 码表项.full.clone(),
+                权重: 编码信息.频率,
+                类型: 条目类型::全码,
+            });
+            if !码表项.short.is_empty() && 码表项.short != 码表项.full {
+                条目列表.push(导出条目 {
+                    词: 码表项.name.clone(),
+                    编码: 码表项.short.clone(),
+                    权重: 编码信息.频率 * 2 + 1,
+                    类型: 条目类型::简码,
+                });
+            }
+        }
+        // 大竹码表沿用原来的写法：全码加括号，简码不加，拆分直接写在编码一栏
+        let 大竹码表 = 条目列表
+            .iter()
+            .map(|x| match x.类型 {
+                条目类型::全码 => (format!("({})", x.编码), x.词.clone()),
+                _ => (x.编码.clone(), x.词.clone()),
+            })
+            .chain(拆分表.iter().map(|(字, 拆分)| (拆分.clone(), 字.clone())))
+            .collect();
+        导出码表 {
+            名称: "冰雪飞花".to_string(),
+            标识: "snow_feihua".to_string(),
+            条目列表,
+            拆分列表: 拆分表.to_vec(),
+            大竹文件名: "dazhu.txt".to_string(),
+            大竹码表,
+            ..Default::default()
+        }
+    }
+
     pub fn 输出码表(
         &self,
         输出目录: &PathBuf,
+        编码结果: &[冰雪飞花编码信息],
         码表: &Vec<码表项>,
         拆分表: &Vec<(String, String)>,
+        导出器列表: &[String],
     ) -> Result<(), 错误> {
         let 码表路径 = 输出目录.join("code.txt");
         写入文本文件(码表路径, 码表);
-        let mut 大竹码表 = vec![];
-        for 码表项 in 码表 {
-            大竹码表.push((format!("({})", 码表项.full.clone()), 码表项.name.clone()));
-        }
-        for 拆分项 in 拆分表 {
-            大竹码表.push((拆分项.1.clone(), 拆分项.0.clone()));
-        }
-        let 大竹码表路径 = 输出目录.join("dazhu.txt");
-        写入文本文件(大竹码表路径, &大竹码表);
+        导出(
+            &self.生成导出码表(编码结果, 码表, 拆分表),
+            导出器列表,
+            输出目录,
+        )?;
         Ok(())
     }
 
@@ -484,6 +805,77 @@ impl 冰雪飞花上下文 {
         for (name, code) in 差指法 {
             writeln!(文件, "- {name} {code}")?;
         }
+        let mut 码长分布 = [(0, 0); 4];
+        for (码表项, 编码信息) in zip(码表, 编码结果) {
+            let 码长 = 码表项.short.chars().count();
+            码长分布[码长 - 1].0 += 1;
+            码长分布[码长 - 1].1 += 编码信息.频率;
+        }
+        writeln!(文件, "\n# 简码码长分布\n")?;
+        writeln!(文件, "| 码长 | 字数 | 频率占比 |")?;
+        writeln!(文件, "| --- | --- | --- |")?;
+        for (码长, (字数, 频率)) in 码长分布.iter().enumerate() {
+            let 占比 = *频率 as f64 / 一字总频率 as f64 * 100.0;
+            writeln!(文件, "| {} | {字数} | {占比:.2}% |", 码长 + 1)?;
+        }
+        // 多音字的各个读音放在一起，看读音在编码空间中的分布
+        let mut 读音列表: IndexMap<char, Vec<usize>> = IndexMap::new();
+        for (序号, 信息) in self.信息列表.iter().enumerate() {
+            读音列表.entry(信息.汉字).or_default().push(序号);
+        }
+        writeln!(文件, "\n# 多音字\n")?;
+        writeln!(文件, "| 字 | 声母 | 频率 | 全码 | 简码 | 备注 |")?;
+        writeln!(文件, "| --- | --- | --- | --- | --- | --- |")?;
+        for (汉字, 序号列表) in 读音列表.iter().filter(|(_, x)| x.len() > 1) {
+            for 序号 in 序号列表 {
+                let 声母 = &self.棱镜.数字转元素[&self.信息列表[*序号].声母];
+                let 备注 = if 编码结果[*序号].同字重码 {
+                    "同字重码"
+                } else if 编码结果[*序号].选重 {
+                    "选重"
+                } else {
+                    ""
+                };
+                writeln!(
+                    文件,
+                    "| {汉字} | {} | {} | {} | {} | {备注} |",
+                    声母.trim_start_matches("声-"),
+                    编码结果[*序号].频率,
+                    码表[*序号].full,
+                    码表[*序号].short
+                )?;
+            }
+        }
         Ok(())
     }
+
+    /// 生成决策的键位图：声母、字根和归并
+    pub fn 生成键盘图(&self, 决策: &冰雪飞花决策) -> 键盘图 {
+        let mut 图 = 键盘图::新建("冰雪飞花".to_string());
+        let 映射 = 决策.线性化(&self.棱镜);
+        for (元素, 安排) in 决策.元素.iter().enumerate() {
+            if 元素 <= 空格 as usize || 映射[元素] == 0 {
+                continue;
+            }
+            let 元素名称 = &self.棱镜.数字转元素[&元素];
+            let 键 = self.棱镜.数字转键[&(映射[元素] as u64)];
+            let (类别, 文本) = match 安排 {
+                冰雪飞花安排::未选取 => continue,
+                冰雪飞花安排::键位(_) => match 元素名称.strip_prefix("声-") {
+                    Some(声母) => (标注类别::声母, 声母.to_string()),
+                    None => (标注类别::主根, 图.字形(元素名称)),
+                },
+                冰雪飞花安排::归并(代表) => (
+                    标注类别::归并,
+                    format!(
+                        "{}→{}",
+                        图.字形(元素名称),
+                        图.字形(&self.棱镜.数字转元素[代表])
+                    ),
+                ),
+            };
+            图.添加(键, 类别, 文本);
+        }
+        图
+    }
 }
@@ -1,33 +1,119 @@
-use crate::feihua::{encoder::冰雪飞花编码器, 冰雪飞花上下文, 冰雪飞花决策};
+use crate::feihua::{
+    encoder::冰雪飞花编码器, 冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花决策变化, 冰雪飞花权重,
+};
 use chai::encoders::编码器;
-use chai::objectives::目标函数;
+use chai::objectives::{metric::指法标记, 目标函数};
+use chai::键位分布信息;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
+use std::iter::zip;
+
+/// 每个不可行字块或不可编码的字在分数上的惩罚，远大于其他指标的量级，使这样的变异总是被拒绝
+const 不可行惩罚: f64 = 1.0;
 
 #[derive(Clone, Serialize)]
 pub struct 冰雪飞花指标 {
     pub 一字全码选重率: f64,
     pub 一字全码选重数: u64,
+    pub 一字简码码长: f64,
+    pub 一字简码组合当量: f64,
+    /// 简码为一到四码的字数
+    pub 一字简码码长分布: [u64; 4],
+    pub 按键分布: BTreeMap<char, f64>,
+    pub 按键分布偏差: f64,
+    pub 同指大跨排率: f64,
+    pub 错手率: f64,
+    /// 多音字的两个读音全码相同的条目数和频率占比，是否计入选重由选项决定
+    pub 同字重码数: u64,
+    pub 同字重码率: f64,
+    /// 没有可行拆分方式的字块，以及全码超出编码空间的字数；不为空时分数加上惩罚
+    pub 不可行字块: Vec<String>,
+    pub 不可编码字数: u64,
 }
 
 impl Display for 冰雪飞花指标 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "一字全码选重率：{:.0}μ，一字全码选重数：{}\n",
+            "一字全码选重率：{:.0}μ，一字全码选重数：{}；一字简码码长：{:.4}，组合当量：{:.2}%，码长分布：{:?}\n",
             self.一字全码选重率 * 1_000_000.0,
-            self.一字全码选重数
-        )
+            self.一字全码选重数,
+            self.一字简码码长,
+            self.一字简码组合当量 * 100.0,
+            self.一字简码码长分布
+        )?;
+        write!(
+            f,
+            "按键分布偏差：{:.2}%，同指大跨排率：{:.2}%，错手率：{:.2}%；",
+            self.按键分布偏差 * 100.0,
+            self.同指大跨排率 * 100.0,
+            self.错手率 * 100.0
+        )?;
+        write!(
+            f,
+            "同字重码数：{}，同字重码率：{:.0}μ；按键分布：",
+            self.同字重码数,
+            self.同字重码率 * 1_000_000.0
+        )?;
+        for (键, 频率) in &self.按键分布 {
+            write!(f, "{键}{:.2} ", 频率 * 100.0)?;
+        }
+        writeln!(f)?;
+        if !self.不可行字块.is_empty() || self.不可编码字数 > 0 {
+            writeln!(
+                f,
+                "不可编码字数：{}；不可行字块：{}",
+                self.不可编码字数,
+                self.不可行字块.join("，")
+            )?;
+        }
+        Ok(())
     }
 }
 
 pub struct 冰雪飞花目标函数 {
     pub 编码器: 冰雪飞花编码器,
+    /// 以下三项都是相邻两码的信息，按 前 + 后 * 进制 索引
+    pub 当量信息: Vec<f64>,
+    pub 同指大跨排: Vec<bool>,
+    pub 错手: Vec<bool>,
+    pub 键位分布信息: 键位分布信息,
+    pub 进制: u64,
+    pub 数字转键: Vec<Option<char>>,
+    pub 权重: 冰雪飞花权重,
 }
 
 impl 冰雪飞花目标函数 {
-    pub fn 新建(_上下文: &冰雪飞花上下文, 编码器: 冰雪飞花编码器) -> Self {
-        Self { 编码器 }
+    pub fn 新建(上下文: &冰雪飞花上下文, 编码器: 冰雪飞花编码器) -> Self {
+        let 棱镜 = &上下文.棱镜;
+        let 指法标记 = 指法标记::new();
+        let 组合数 = (棱镜.进制 * 棱镜.进制) as usize;
+        let mut 当量信息 = vec![0.0; 组合数];
+        let mut 同指大跨排 = vec![false; 组合数];
+        let mut 错手 = vec![false; 组合数];
+        for (前, 前键) in &棱镜.数字转键 {
+            for (后, 后键) in &棱镜.数字转键 {
+                let 索引 = (前 + 后 * 棱镜.进制) as usize;
+                let 组合: String = [*前键, *后键].iter().collect();
+                当量信息[索引] = 上下文.原始当量信息.get(&组合).copied().unwrap_or(0.0);
+                同指大跨排[索引] = 指法标记.同指大跨排.contains(&(*前键, *后键));
+                错手[索引] = 指法标记.错手.contains(&(*前键, *后键));
+            }
+        }
+        let 数字转键 = (0..棱镜.进制)
+            .map(|x| 棱镜.数字转键.get(&x).cloned())
+            .collect();
+        Self {
+            编码器,
+            当量信息,
+            同指大跨排,
+            错手,
+            键位分布信息: 棱镜.预处理键位分布信息(&上下文.原始键位分布信息),
+            进制: 棱镜.进制,
+            数字转键,
+            权重: 上下文.权重.clone(),
+        }
     }
 }
 
@@ -37,31 +123,116 @@ impl 目标函数 for 冰雪飞花目标函数 {
 
     fn 计算(
         &mut self,
-        决策: &crate::feihua::冰雪飞花决策,
-        _变化: &Option<()>,
+        决策: &Self::决策,
+        变化: &Option<冰雪飞花决策变化>,
     ) -> (Self::目标值, f64) {
-        self.编码器.编码(决策, _变化, &mut []);
+        self.编码器.编码(决策, 变化, &mut []);
         let mut 一字总频率 = 0;
         let mut 一字全码选重频率 = 0;
         let mut 一字总指数频率 = 0.0;
         let mut 一字全码选重指数频率 = 0.0;
         let mut 一字全码选重数 = 0;
+        let mut 简码总键数 = 0;
+        let mut 简码总频率 = 0;
+        let mut 简码总组合数 = 0;
+        let mut 简码总组合当量 = 0.0;
+        let mut 同指大跨排频率 = 0;
+        let mut 错手频率 = 0;
+        let mut 按键频率 = vec![0; self.进制 as usize];
+        let mut 一字简码码长分布 = [0; 4];
+        let mut 同字重码数 = 0;
+        let mut 同字重码频率 = 0;
+        let mut 不可编码字数 = 0;
         for 编码信息 in &self.编码器.编码结果 {
+            if 编码信息.不可编码 {
+                不可编码字数 += 1;
+                一字总频率 += 编码信息.频率;
+                一字总指数频率 += 编码信息.指数频率;
+                continue;
+            }
             一字总频率 += 编码信息.频率;
             一字总指数频率 += 编码信息.指数频率;
+            if 编码信息.同字重码 {
+                同字重码数 += 1;
+                同字重码频率 += 编码信息.频率;
+            }
             if 编码信息.选重 {
                 一字全码选重频率 += 编码信息.频率;
                 一字全码选重指数频率 += 编码信息.指数频率;
                 一字全码选重数 += 1;
             }
+            // 并入其他读音的是码表中的同一个条目，不重复计入码长和手感
+            if 编码信息.并入读音.is_some() {
+                continue;
+            }
+            let 简码: Vec<_> = 编码信息.简码.0.into_iter().filter(|x| *x != 0).collect();
+            简码总频率 += 编码信息.频率;
+            简码总键数 += 简码.len() as u64 * 编码信息.频率;
+            简码总组合数 += (简码.len() as u64 - 1) * 编码信息.频率;
+            for 键 in &简码 {
+                按键频率[*键 as usize] += 编码信息.频率;
+            }
+            for 组合 in 简码.windows(2) {
+                let 索引 = (组合[0] as u64 + 组合[1] as u64 * self.进制) as usize;
+                简码总组合当量 += self.当量信息[索引] * 编码信息.频率 as f64;
+                if self.同指大跨排[索引] {
+                    同指大跨排频率 += 编码信息.频率;
+                }
+                if self.错手[索引] {
+                    错手频率 += 编码信息.频率;
+                }
+            }
+            一字简码码长分布[简码.len() - 1] += 1;
         }
         let 一字全码选重率 = 一字全码选重频率 as f64 / 一字总频率 as f64;
         let 一字全码指数选重率 = 一字全码选重指数频率 / 一字总指数频率;
         // let 一字全码静态选重率 = 一字全码选重数 as f64 / self.编码器.汉字信息.len() as f64;
-        let 分数 = 一字全码指数选重率;
+        let 一字简码码长 = 简码总键数 as f64 / 简码总频率 as f64;
+        let 一字简码组合当量 = 简码总组合当量 / 简码总组合数 as f64;
+        let 同指大跨排率 = 同指大跨排频率 as f64 / 简码总组合数 as f64;
+        let 错手率 = 错手频率 as f64 / 简码总组合数 as f64;
+        let mut 按键分布 = BTreeMap::new();
+        let mut 按键分布偏差 = 0.0;
+        for (键, (频率, 损失函数)) in zip(&按键频率, &self.键位分布信息).enumerate()
+        {
+            let 频率 = *频率 as f64 / 简码总键数 as f64;
+            let 差距 = 频率 - 损失函数.ideal;
+            if 差距 > 0.0 {
+                按键分布偏差 += 损失函数.gt_penalty * 差距;
+            } else {
+                按键分布偏差 -= 损失函数.lt_penalty * 差距;
+            }
+            if let Some(键) = self.数字转键[键] {
+                按键分布.insert(键, 频率);
+            }
+        }
+        let 权重 = &self.权重;
+        let 分数 = 一字全码指数选重率 * 权重.选重率
+            + 一字简码码长 * 权重.码长
+            + 一字简码组合当量 * 权重.组合当量
+            + 按键分布偏差 * 权重.按键分布偏差
+            + 同指大跨排率 * 权重.同指大跨排
+            + 错手率 * 权重.错手
+            + (self.编码器.不可行块.len() as u64 + 不可编码字数) as f64 * 不可行惩罚;
+        let mut 不可行字块: Vec<_> = self.编码器.不可行块.iter().cloned().collect();
+        不可行字块.sort();
         let 指标 = 冰雪飞花指标 {
             一字全码选重率,
             一字全码选重数,
+            一字简码码长,
+            一字简码组合当量,
+            一字简码码长分布,
+            按键分布,
+            按键分布偏差,
+            同指大跨排率,
+            错手率,
+            同字重码数,
+            同字重码率: 同字重码频率 as f64 / 一字总频率 as f64,
+            不可行字块: 不可行字块
+                .into_iter()
+                .map(|x| self.编码器.描述字块(x))
+                .collect(),
+            不可编码字数,
         };
         (指标, 分数)
     }
@@ -1,9 +1,10 @@
 use crate::feihua::{
-    冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花决策空间, 冰雪飞花安排
+    冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花决策变化, 冰雪飞花决策空间, 冰雪飞花安排,
 };
 use chai::{operators::变异, 棱镜};
 use rand::{
-    random, rng,
+    Rng, SeedableRng,
+    rngs::StdRng,
     seq::{IndexedRandom, IteratorRandom},
 };
 use std::iter::zip;
@@ -11,14 +12,20 @@ use std::iter::zip;
 pub struct 冰雪飞花操作 {
     pub 棱镜: 棱镜,
     pub 决策空间: 冰雪飞花决策空间,
+    /// 每个部首组的候选部首数
+    pub 部首候选数: Vec<usize>,
+    rng: StdRng,
 }
 
 impl 变异 for 冰雪飞花操作 {
     type 决策 = 冰雪飞花决策;
 
-    fn 变异(&mut self, 决策: &mut 冰雪飞花决策) {
-        let _r = random::<f64>();
-        self.移动字根(决策);
+    fn 变异(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = self.rng.random::<f64>();
+        if r < 0.1 && !self.部首候选数.is_empty() {
+            return self.变更部首(决策);
+        }
+        self.移动字根(决策)
         // if r < 2.0 {
         //     self.移动字根(决策);
         // } else {
@@ -32,15 +39,17 @@ impl 变异 for 冰雪飞花操作 {
 }
 
 impl 冰雪飞花操作 {
-    pub fn 新建(上下文: &冰雪飞花上下文) -> Self {
+    pub fn 新建(上下文: &冰雪飞花上下文, 种子: u64) -> Self {
         冰雪飞花操作 {
             棱镜: 上下文.棱镜.clone(),
             决策空间: 上下文.决策空间.clone(),
+            部首候选数: 上下文.部首组列表.iter().map(|x| x.候选.len()).collect(),
+            rng: StdRng::seed_from_u64(种子),
         }
     }
 
-    pub fn 移动字根(&self, 决策: &mut 冰雪飞花决策) {
-        let mut r = rng();
+    pub fn 移动字根(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = &mut self.rng;
         let mut 可行移动 = vec![];
         for ((元素, 当前安排), 安排列表) in
             zip(决策.元素.iter().enumerate(), self.决策空间.元素空间.iter())
@@ -48,33 +57,58 @@ impl 冰雪飞花操作 {
             let 新安排 = 安排列表
                 .iter()
                 .filter(|x| x.安排 != *当前安排 && x.安排 != 冰雪飞花安排::未选取)
-                .choose(&mut r);
+                .choose(r);
             if let Some(新安排) = 新安排 {
                 可行移动.push((元素, 新安排.安排));
             }
         }
-        let (元素, 新安排) = 可行移动.choose(&mut r).unwrap();
+        let (元素, 新安排) = 可行移动.choose(r).unwrap();
         决策.元素[*元素] = *新安排;
+        冰雪飞花决策变化 {
+            字根: vec![*元素],
+            ..Default::default()
+        }
+    }
+
+    /// 让一个部首组改用另一个候选部首，组内的字一起改变
+    pub fn 变更部首(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = &mut self.rng;
+        let 组 = (0..self.部首候选数.len()).choose(r).unwrap();
+        let Some(新部首) = (0..self.部首候选数[组])
+            .filter(|x| *x != 决策.部首[组])
+            .choose(r)
+        else {
+            return 冰雪飞花决策变化::无变化();
+        };
+        决策.部首[组] = 新部首;
+        冰雪飞花决策变化 {
+            部首组: vec![组],
+            ..Default::default()
+        }
     }
 
-    pub fn 交换字根(&self, 决策: &mut 冰雪飞花决策) {
-        let mut r = rng();
+    pub fn 交换字根(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = &mut self.rng;
         let mut 可行交换 = vec![];
         for (元素, 当前安排) in 决策.元素.iter().enumerate() {
             if let 冰雪飞花安排::键位(_) = 当前安排 {
                 可行交换.push(元素);
             }
         }
-        let 交换: Vec<_> = 可行交换.choose_multiple(&mut r, 2).cloned().collect();
+        let 交换: Vec<_> = 可行交换.choose_multiple(r, 2).cloned().collect();
         if 交换.len() == 2 {
             let (字根一, 字根二) = (交换[0], 交换[1]);
-            决策.元素[字根一] = 决策.元素[字根二];
-            决策.元素[字根二] = 决策.元素[字根一];
+            决策.元素.swap(字根一, 字根二);
+            return 冰雪飞花决策变化 {
+                字根: vec![字根一, 字根二],
+                ..Default::default()
+            };
         }
+        冰雪飞花决策变化::无变化()
     }
 
-    pub fn 增加字根(&self, 决策: &mut 冰雪飞花决策) {
-        let mut r = rng();
+    pub fn 增加字根(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = &mut self.rng;
         let mut 可行增加 = vec![];
         for ((元素, 当前安排), 安排列表) in
             zip(决策.元素.iter().enumerate(), self.决策空间.元素空间.iter())
@@ -83,18 +117,23 @@ impl 冰雪飞花操作 {
                 let 新安排 = 安排列表
                     .iter()
                     .filter(|x| x.安排 != 冰雪飞花安排::未选取)
-                    .choose(&mut r)
+                    .choose(r)
                     .unwrap();
                 可行增加.push((元素, 新安排.安排));
             }
         }
-        if let Some((元素, 新安排)) = 可行增加.choose(&mut r) {
+        if let Some((元素, 新安排)) = 可行增加.choose(r) {
             决策.元素[*元素] = *新安排;
+            return 冰雪飞花决策变化 {
+                字根: vec![*元素],
+                ..Default::default()
+            };
         }
+        冰雪飞花决策变化::无变化()
     }
 
-    pub fn 删除字根(&self, 决策: &mut 冰雪飞花决策) {
-        let mut r = rng();
+    pub fn 删除字根(&mut self, 决策: &mut 冰雪飞花决策) -> 冰雪飞花决策变化 {
+        let r = &mut self.rng;
         let mut 可行删除 = vec![];
         for ((元素, 当前安排), 安排列表) in
             zip(决策.元素.iter().enumerate(), self.决策空间.元素空间.iter())
@@ -106,8 +145,13 @@ impl 冰雪飞花操作 {
                 可行删除.push(元素);
             }
         }
-        if let Some(元素) = 可行删除.choose(&mut r) {
+        if let Some(元素) = 可行删除.choose(r) {
             决策.元素[*元素] = 冰雪飞花安排::未选取;
+            return 冰雪飞花决策变化 {
+                字根: vec![*元素],
+                ..Default::default()
+            };
         }
+        冰雪飞花决策变化::无变化()
     }
 }
@@ -1,4 +1,6 @@
+pub mod chart;
 pub mod common;
+pub mod exporters;
 pub mod snow4;
 pub mod snow2;
 pub mod qingyun;
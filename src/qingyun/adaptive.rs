@@ -0,0 +1,265 @@
+//! 冰雪清韵变异操作的自适应选择。
+//!
+//! 退火过程中不同的变异操作在不同阶段的作用不同：前期改变补码键、交换主根这类大幅度的操作更有用，
+//! 后期则主要依靠移动副根做细调。这里用多臂老虎机的概率匹配方法，根据每种操作近期的接受率和改进率
+//! 在线调整选择概率。
+
+use crate::qingyun::{元素安排, 冰雪清韵决策};
+use chai::元素;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum 变异算子 {
+    改变补码键,
+    交换主副根,
+    交换主根,
+    移动笔画,
+    产生副根,
+    湮灭副根,
+    移动副根,
+}
+
+impl 变异算子 {
+    pub const 全部: [变异算子; 7] = [
+        变异算子::改变补码键,
+        变异算子::交换主副根,
+        变异算子::交换主根,
+        变异算子::移动笔画,
+        变异算子::产生副根,
+        变异算子::湮灭副根,
+        变异算子::移动副根,
+    ];
+
+    /// 非自适应模式下各操作的固定选择概率
+    pub const 固定概率: [f64; 7] = [0.05, 0.15, 0.05, 0.05, 0.25, 0.25, 0.20];
+
+    pub fn 序号(&self) -> usize {
+        *self as usize
+    }
+
+    /// 按照给定的概率分布选出一个操作，随机数应在 [0, 1) 之间
+    pub fn 按概率选择(概率: &[f64; 7], 随机数: f64) -> Self {
+        let mut 累计 = 0.0;
+        for (算子, 概率) in Self::全部.iter().zip(概率) {
+            累计 += 概率;
+            if 随机数 < 累计 {
+                return *算子;
+            }
+        }
+        Self::全部[Self::全部.len() - 1]
+    }
+}
+
+impl Display for 变异算子 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// 目标函数和操作之间共享的最近一次评估的分数
+///
+/// 退火时目标函数和操作在同一个线程中交替调用，操作在下一次变异前读取上一次候选解的分数。
+#[derive(Debug, Clone, Default)]
+pub struct 分数记录(Arc<AtomicU64>);
+
+impl 分数记录 {
+    pub fn 写入(&self, 分数: f64) {
+        self.0.store(分数.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn 读取(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct 算子统计 {
+    信用: f64,
+    接受率: f64,
+    改进率: f64,
+    使用次数: u64,
+    接受次数: u64,
+    改进次数: u64,
+}
+
+/// 候选解中改动过的元素的安排，以及补码键和两个主根小码
+///
+/// chai 不把是否接受告诉变异操作，而下一次变异拿到的是当前解：只要这些值和当前解一致，
+/// 候选解就被接受了。这样每步只比较改动过的几个元素，不需要复制整个决策。
+/// 没有改变任何值的候选解被拒绝后当前解也和它一致，无法判断是否接受，一律按未接受结算。
+struct 候选 {
+    算子: 变异算子,
+    安排: Vec<(元素, 元素安排)>,
+    键: [char; 3],
+    无改动: bool,
+}
+
+fn 键(决策: &冰雪清韵决策) -> [char; 3] {
+    [决策.补码键, 决策.第一主根, 决策.第二主根]
+}
+
+pub struct 自适应选择器 {
+    统计: Vec<算子统计>,
+    概率: [f64; 7],
+    分数记录: 分数记录,
+    当前分数: Option<f64>,
+    候选: Option<候选>,
+    步数: u64,
+    日志: Option<File>,
+}
+
+/// 近期统计的指数衰减系数，约等于只看最近 200 次使用
+const 衰减: f64 = 0.005;
+/// 每种操作的最低选择概率，保证被冷落的操作仍有机会重新被评估
+const 最小概率: f64 = 0.02;
+/// 被接受但没有改进的操作得到的奖励，鼓励在平台区继续探索
+const 接受奖励: f64 = 0.1;
+const 日志间隔: u64 = 1000;
+
+impl 自适应选择器 {
+    pub fn 新建(分数记录: 分数记录) -> Self {
+        let 统计 = 变异算子::固定概率
+            .iter()
+            .map(|&概率| 算子统计 {
+                信用: 概率,
+                ..Default::default()
+            })
+            .collect();
+        Self {
+            统计,
+            概率: 变异算子::固定概率,
+            分数记录,
+            当前分数: None,
+            候选: None,
+            步数: 0,
+            日志: None,
+        }
+    }
+
+    /// 日志文件无法创建时只打印提示，不记录权重变化，不影响优化本身
+    pub fn 设置日志(&mut self, 路径: PathBuf) {
+        let 表头: Vec<_> = 变异算子::全部
+            .iter()
+            .map(|x| format!("{x}概率\t{x}接受率\t{x}改进率"))
+            .collect();
+        let 结果 = File::create(&路径).and_then(|mut 文件| {
+            writeln!(文件, "步数\t{}", 表头.join("\t"))?;
+            Ok(文件)
+        });
+        match 结果 {
+            Ok(文件) => self.日志 = Some(文件),
+            Err(e) => println!(
+                "无法创建变异权重日志 {}：{e}，不记录权重变化",
+                路径.display()
+            ),
+        }
+    }
+
+    /// 在下一次变异之前，根据当前决策判断上一个候选解是否被接受，并更新对应操作的统计
+    pub fn 结算(&mut self, 决策: &冰雪清韵决策) {
+        let 分数 = self.分数记录.读取();
+        let Some(候选) = self.候选.take() else {
+            self.当前分数 = Some(分数);
+            return;
+        };
+        let 接受 = !候选.无改动
+            && 候选.键 == 键(决策)
+            && 候选.安排.iter().all(|(元素, 安排)| 决策.元素[*元素] == *安排);
+        let 改进 = 接受 && self.当前分数.is_none_or(|x| 分数 < x);
+        if 接受 {
+            self.当前分数 = Some(分数);
+        }
+        let 奖励 = if 改进 {
+            1.0
+        } else if 接受 {
+            接受奖励
+        } else {
+            0.0
+        };
+        let 统计 = &mut self.统计[候选.算子.序号()];
+        统计.使用次数 += 1;
+        统计.接受次数 += 接受 as u64;
+        统计.改进次数 += 改进 as u64;
+        统计.信用 += 衰减 * (奖励 - 统计.信用);
+        统计.接受率 += 衰减 * (接受 as u64 as f64 - 统计.接受率);
+        统计.改进率 += 衰减 * (改进 as u64 as f64 - 统计.改进率);
+        self.更新概率();
+        self.步数 += 1;
+        if self.步数 % 日志间隔 == 0 {
+            self.写入日志();
+        }
+    }
+
+    pub fn 选择(&self, 随机数: f64) -> 变异算子 {
+        变异算子::按概率选择(&self.概率, 随机数)
+    }
+
+    /// `改动元素` 是这次变异改写过的元素，可以有重复；`无改动` 表示候选解和原来的解完全相同
+    pub fn 记录候选(
+        &mut self,
+        算子: 变异算子,
+        改动元素: impl Iterator<Item = 元素>,
+        决策: &冰雪清韵决策,
+        无改动: bool,
+    ) {
+        self.候选 = Some(候选 {
+            算子,
+            安排: 改动元素.map(|x| (x, 决策.元素[x])).collect(),
+            键: 键(决策),
+            无改动,
+        });
+    }
+
+    fn 更新概率(&mut self) {
+        let 总信用: f64 = self.统计.iter().map(|x| x.信用).sum();
+        let 可分配 = 1.0 - 最小概率 * self.统计.len() as f64;
+        for (概率, 统计) in self.概率.iter_mut().zip(&self.统计) {
+            *概率 = if 总信用 > 0.0 {
+                最小概率 + 可分配 * 统计.信用 / 总信用
+            } else {
+                1.0 / 变异算子::全部.len() as f64
+            };
+        }
+    }
+
+    fn 写入日志(&mut self) {
+        let Some(文件) = &mut self.日志 else {
+            return;
+        };
+        let 列: Vec<_> = self
+            .概率
+            .iter()
+            .zip(&self.统计)
+            .map(|(概率, 统计)| {
+                format!("{:.4}\t{:.4}\t{:.4}", 概率, 统计.接受率, 统计.改进率)
+            })
+            .collect();
+        if let Err(e) = writeln!(文件, "{}\t{}", self.步数, 列.join("\t")) {
+            println!("无法写入变异权重日志：{e}，不再记录权重变化");
+            self.日志 = None;
+        }
+    }
+
+    pub fn 总结(&self) -> String {
+        let mut 行 = vec![];
+        for ((算子, 概率), 统计) in 变异算子::全部.iter().zip(&self.概率).zip(&self.统计) {
+            行.push(format!(
+                "{算子}：概率 {:.2}%，使用 {} 次，接受 {} 次，改进 {} 次",
+                概率 * 100.0,
+                统计.使用次数,
+                统计.接受次数,
+                统计.改进次数
+            ));
+        }
+        行.join("\n")
+    }
+}
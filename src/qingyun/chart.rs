@@ -0,0 +1,64 @@
+use crate::{
+    chart::{标注类别, 键盘图},
+    qingyun::{
+        context::冰雪清韵上下文, 元素安排, 冰雪清韵决策, 大集合, 小集合
+    },
+};
+
+impl 冰雪清韵上下文 {
+    /// 生成决策的键位图：声母、韵母、主根（上标表示用第一或第二主根的小码）、副根（附声韵拼写）和归并
+    pub fn 生成键盘图(&self, 决策: &冰雪清韵决策) -> 键盘图 {
+        let mut 图 = 键盘图::新建(format!(
+            "冰雪清韵　补码键 {}　第一主根 {}　第二主根 {}",
+            决策.补码键, 决策.第一主根, 决策.第二主根
+        ));
+        let 映射 = 决策.线性化(&self.棱镜);
+        let 名称 = |元素: &usize| self.棱镜.数字转元素[元素].clone();
+        let 去前缀 = |元素: &usize| {
+            let 名称 = 名称(元素);
+            名称
+                .split_once('-')
+                .map(|(_, x)| x.to_string())
+                .unwrap_or(名称)
+        };
+        for (元素, 安排) in 决策.元素.iter().enumerate() {
+            // 前面的元素是键本身
+            if 元素 <= 大集合.len() + 小集合.len() {
+                continue;
+            }
+            let Some(元素名称) = self.棱镜.数字转元素.get(&元素) else {
+                continue;
+            };
+            if 映射[元素].0 == 0 {
+                continue;
+            }
+            let 键 = self.棱镜.数字转键[&(映射[元素].0 as u64)];
+            let 字形 = 图.字形(元素名称);
+            let (类别, 文本) = match 安排 {
+                元素安排::未选取 => continue,
+                元素安排::键位(_) if 元素名称.starts_with("声-") => {
+                    (标注类别::声母, 去前缀(&元素))
+                }
+                元素安排::键位(_) if 元素名称.starts_with("韵-") => {
+                    (标注类别::韵母, 去前缀(&元素))
+                }
+                元素安排::键位(_) => (标注类别::主根, 字形),
+                元素安排::键位第一(_) => (标注类别::主根, format!("{字形}¹")),
+                元素安排::键位第二(_) => (标注类别::主根, format!("{字形}²")),
+                元素安排::声母韵母 { 声母, 韵母 } => (
+                    标注类别::副根,
+                    format!("{字形}{}{}", 去前缀(声母), 去前缀(韵母)),
+                ),
+                元素安排::归并(代表) => {
+                    (标注类别::归并, format!("{字形}→{}", 图.字形(&名称(代表))))
+                }
+                元素安排::归并韵母 { 字根, 韵母 } => (
+                    标注类别::归并,
+                    format!("{字形}→{}{}", 图.字形(&名称(字根)), 去前缀(韵母)),
+                ),
+            };
+            图.添加(键, 类别, 文本);
+        }
+        图
+    }
+}
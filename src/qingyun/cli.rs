@@ -0,0 +1,177 @@
+use crate::chart::键盘图;
+use crate::common::{冰雪命令行参数, 方案, 运行方案};
+use crate::qingyun::adaptive::分数记录;
+use crate::qingyun::context::冰雪清韵上下文;
+use crate::qingyun::diff::差异报告;
+use crate::qingyun::encoder::冰雪清韵编码器;
+use crate::qingyun::objective::冰雪清韵目标函数;
+use crate::qingyun::operators::冰雪清韵操作;
+use crate::qingyun::冰雪清韵决策;
+use chai::config::配置;
+use chai::interfaces::command_line::{从命令行参数创建, 命令行};
+use chai::interfaces::默认输入;
+use chai::objectives::目标函数;
+use chai::错误;
+use clap::Parser;
+use serde_yaml::from_str;
+use std::fs::{read_to_string, write};
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+/// `qingyun diff <a.yaml> <b.yaml> [其他参数]`，其他参数与 encode 相同
+#[derive(Parser)]
+#[command(name = "qingyun diff")]
+struct 比较命令行参数 {
+    /// 作为基准的方案配置
+    甲: PathBuf,
+    /// 要比较的方案配置
+    乙: PathBuf,
+    /// 报告的输出路径
+    #[arg(short, long, default_value = "output/差异.md")]
+    输出: PathBuf,
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    其他: Vec<String>,
+}
+
+fn 比较(参数: 比较命令行参数) -> Result<(), 错误> {
+    let mut 默认参数 = vec!["qingyun".to_string(), 参数.甲.display().to_string()];
+    默认参数.extend(参数.其他);
+    默认参数.push("encode".to_string());
+    let 默认参数 = 冰雪命令行参数::parse_from(默认参数).默认;
+    let 上下文 = 冰雪清韵上下文::新建(从命令行参数创建(&默认参数))?;
+    let 配置乙: 配置 = from_str(&read_to_string(&参数.乙)?).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("无法解析 {}：{e}", 参数.乙.display()),
+        )
+    })?;
+    let 决策甲 = 上下文.初始决策.clone();
+    let 决策乙 = 上下文.读取决策(&配置乙)?;
+    let mut 目标函数甲 =
+        冰雪清韵目标函数::新建(&上下文, 冰雪清韵编码器::新建(&上下文, true)?);
+    let mut 目标函数乙 =
+        冰雪清韵目标函数::新建(&上下文, 冰雪清韵编码器::新建(&上下文, true)?);
+    目标函数甲.计算(&决策甲, &None);
+    目标函数乙.计算(&决策乙, &None);
+    let 报告 = 差异报告::新建(
+        &上下文,
+        &决策甲,
+        &目标函数甲.编码器,
+        &决策乙,
+        &目标函数乙.编码器,
+    );
+    if let Some(目录) = 参数.输出.parent() {
+        std::fs::create_dir_all(目录)?;
+    }
+    报告.写入(&参数.输出)?;
+    println!(
+        "全码重学代价 {:.2}%，简码重学代价 {:.2}%，详见 {}",
+        报告.全码重学代价 * 100.0,
+        报告.简码重学代价 * 100.0,
+        参数.输出.display()
+    );
+    Ok(())
+}
+
+/// 冰雪清韵作为 `方案`：优化时可以开启自适应变异
+pub struct 冰雪清韵方案 {
+    pub 上下文: 冰雪清韵上下文,
+    pub 自适应: bool,
+}
+
+impl 方案 for 冰雪清韵方案 {
+    type 决策 = 冰雪清韵决策;
+    type 上下文 = 冰雪清韵上下文;
+    type 编码器 = 冰雪清韵编码器;
+    type 目标函数 = 冰雪清韵目标函数;
+    type 操作 = 冰雪清韵操作;
+
+    const 默认导出器: &'static [&'static str] = 冰雪清韵上下文::默认导出器;
+
+    fn 新建(
+        命令行参数: &冰雪命令行参数, 输入: 默认输入
+    ) -> Result<Self, 错误> {
+        Ok(Self {
+            上下文: 冰雪清韵上下文::新建(输入)?,
+            自适应: 命令行参数.adaptive,
+        })
+    }
+
+    fn 上下文(&self) -> &冰雪清韵上下文 {
+        &self.上下文
+    }
+
+    fn 配置(&self) -> &配置 {
+        &self.上下文.配置
+    }
+
+    fn 初始决策(&self) -> &冰雪清韵决策 {
+        &self.上下文.初始决策
+    }
+
+    // 优化时只给需要的字出简，输出时全部出简
+    fn 新建编码器(&self, 输出: bool) -> Result<冰雪清韵编码器, 错误> {
+        冰雪清韵编码器::新建(&self.上下文, 输出)
+    }
+
+    fn 新建目标函数(
+        &self,
+        编码器: 冰雪清韵编码器,
+    ) -> Result<冰雪清韵目标函数, 错误> {
+        Ok(冰雪清韵目标函数::新建(&self.上下文, 编码器))
+    }
+
+    fn 新建操作(
+        &self,
+        目标函数: &mut 冰雪清韵目标函数,
+        种子: u64,
+        子命令行: &命令行,
+    ) -> 冰雪清韵操作 {
+        let mut 操作 = 冰雪清韵操作::新建(&self.上下文, 种子);
+        if self.自适应 {
+            let 记录 = 分数记录::default();
+            目标函数.分数记录 = Some(记录.clone());
+            操作.启用自适应(记录, 子命令行.输出目录.join("变异权重.txt"));
+        }
+        操作
+    }
+
+    fn 输出码表(
+        &self,
+        目标函数: &冰雪清韵目标函数,
+        输出目录: &PathBuf,
+        导出器列表: &[String],
+    ) -> Result<(), 错误> {
+        let 编码结果 = &目标函数.编码器.编码结果;
+        self.上下文
+            .生成码表(编码结果, Some(输出目录.clone()), 导出器列表)?;
+        Ok(())
+    }
+
+    fn 分析码表(
+        &self, 目标函数: &冰雪清韵目标函数, 输出目录: &PathBuf
+    ) -> Result<(), 错误> {
+        self.上下文
+            .分析码表(&目标函数.编码器.编码结果, Some(输出目录.clone()))
+    }
+
+    fn 生成键盘图(&self, 决策: &冰雪清韵决策) -> 键盘图 {
+        self.上下文.生成键盘图(决策)
+    }
+
+    fn 写出操作记录(
+        &self, 操作: &冰雪清韵操作, 输出目录: &PathBuf
+    ) -> Result<(), 错误> {
+        write(输出目录.join("变异总结.txt"), 操作.总结())?;
+        Ok(())
+    }
+}
+
+/// 运行冰雪清韵，`参数` 的第一项是程序名
+pub fn 运行(参数: Vec<String>) -> Result<(), 错误> {
+    // chai 的子命令不能扩展，diff 在解析默认命令行参数之前单独处理
+    if 参数.get(1).map(String::as_str) == Some("diff") {
+        return 比较(比较命令行参数::parse_from(&参数[1..]));
+    }
+    运行方案::<冰雪清韵方案>(参数)
+}
@@ -1,3 +1,6 @@
+use crate::exporters::{导出, 导出条目, 导出码表, 条目类型};
+use crate::qingyun::matching::最大权匹配;
+use crate::qingyun::report::{分析截断, 分析报告, 分析条目, 重码组};
 use crate::qingyun::{
     encoder::简码覆盖, 不好的大集合键, 元素安排, 冰雪清韵决策, 冰雪清韵决策空间, 冰雪清韵编码信息,
     动态拆分项, 原始音节信息, 固定拆分项, 大集合, 小集合, 常用简繁范围, 拆分输入, 条件,
@@ -21,8 +24,8 @@ use serde::Serialize;
 use serde_yaml::{from_str, to_string};
 use std::{
     cmp::Reverse,
-    fs::{File, read_to_string},
-    io::Write,
+    fs::read_to_string,
+    io::{Error, ErrorKind},
     path::PathBuf,
 };
 
@@ -151,21 +154,7 @@ impl 冰雪清韵上下文 {
             韵母: vec![],
             字根: vec![],
         };
-        let Mapped::Basic(补码键) = 原始决策["补码-1"].clone() else {
-            panic!("补码键必须指定");
-        };
-        let Mapped::Basic(第一主根) = 原始决策["主根-1"].clone() else {
-            panic!("第一主根必须指定");
-        };
-        let Mapped::Basic(第二主根) = 原始决策["主根-2"].clone() else {
-            panic!("第二主根必须指定");
-        };
-        let mut 初始决策 = 冰雪清韵决策 {
-            元素: vec![元素安排::未选取; 最大数量],
-            补码键: 补码键.chars().next().unwrap(),
-            第一主根: 第一主根.chars().next().unwrap(),
-            第二主根: 第二主根.chars().next().unwrap(),
-        };
+        let 初始决策 = Self::解析决策(&原始决策, &棱镜)?;
         for 元素 in &所有元素 {
             let 序号 = 棱镜.元素转数字[元素];
             let 编码 = 原始决策.get(元素).unwrap_or(&Mapped::Unused(()));
@@ -174,11 +163,9 @@ impl 冰雪清韵上下文 {
             }
             if 元素.starts_with("声") {
                 决策空间.声母.push(序号);
-                let Mapped::Basic(编码) = 编码 else {
+                let Mapped::Basic(_) = 编码 else {
                     unreachable!();
                 };
-                let 键位 = 编码.chars().next().unwrap();
-                初始决策.元素[序号] = 元素安排::键位(键位);
                 match 元素.as_str() {
                     "声-zh" | "声-ch" | "声-sh" | "声-0" => {
                         决策空间.元素[序号] = 大集合
@@ -193,16 +180,13 @@ impl 冰雪清韵上下文 {
                 }
             } else if 元素.starts_with("韵") {
                 决策空间.韵母.push(序号);
-                if let Mapped::Grouped { element } = 编码.clone() {
-                    初始决策.元素[序号] = 元素安排::归并(棱镜.元素转数字[&element]);
+                if let Mapped::Grouped { .. } = 编码 {
                     决策空间.元素[序号] = vec![初始决策.元素[序号].clone().into()];
                 } else {
-                    let Mapped::Basic(编码) = 编码 else {
+                    let Mapped::Basic(_) = 编码 else {
                         println!("元素 {} 的编码不是 Basic 或 Grouped", 元素);
                         unreachable!();
                     };
-                    let 键位 = 编码.chars().next().unwrap();
-                    初始决策.元素[序号] = 元素安排::键位(键位);
                     match 元素.as_str() {
                         "韵-a" | "韵-e" | "韵-i" | "韵-o" | "韵-u" => {
                             决策空间.元素[序号] = vec![初始决策.元素[序号].clone().into()];
@@ -347,7 +331,6 @@ impl 冰雪清韵上下文 {
                     }
                 }
                 let 安排列表: Vec<_> = 安排列表.into_iter().collect();
-                初始决策.元素[序号] = 元素安排::from(当前决策, &棱镜);
                 决策空间.元素[序号] = 安排列表;
             }
         }
@@ -391,6 +374,39 @@ impl 冰雪清韵上下文 {
         })
     }
 
+    /// 把配置中的映射按棱镜读成决策，补码键和两个主根单独存放
+    fn 解析决策(
+        原始决策: &IndexMap<String, Mapped>,
+        棱镜: &棱镜,
+    ) -> Result<冰雪清韵决策, 错误> {
+        let 无效 = |消息: String| -> 错误 { Error::new(ErrorKind::InvalidData, 消息).into() };
+        let 读取键 = |名称: &str| match 原始决策.get(名称) {
+            Some(Mapped::Basic(键)) if !键.is_empty() => Ok(键.chars().next().unwrap()),
+            _ => Err(无效(format!("{名称} 必须指定为一个键位"))),
+        };
+        let mut 决策 = 冰雪清韵决策 {
+            元素: vec![元素安排::未选取; 棱镜.数字转元素.len() + 1],
+            补码键: 读取键("补码-1")?,
+            第一主根: 读取键("主根-1")?,
+            第二主根: 读取键("主根-2")?,
+        };
+        // 不在 data/rules.yaml 中的元素不参与拆分，直接忽略
+        for (元素, 安排) in 原始决策 {
+            if ["补码-1", "主根-1", "主根-2"].contains(&元素.as_str()) {
+                continue;
+            }
+            if let Some(&序号) = 棱镜.元素转数字.get(元素) {
+                决策.元素[序号] = 元素安排::from(安排, 棱镜);
+            }
+        }
+        Ok(决策)
+    }
+
+    /// 在这个上下文中读取另一份配置的决策，用于比较两个方案
+    pub fn 读取决策(&self, 配置: &配置) -> Result<冰雪清韵决策, 错误> {
+        Self::解析决策(&配置.form.mapping, &self.棱镜)
+    }
+
     pub fn 预处理当量信息(&self) -> Vec<f32> {
         let mut 当量信息 = vec![0.0; 编码::编码空间大小()];
         let s = vec![];
@@ -615,7 +631,7 @@ impl 冰雪清韵上下文 {
         (固定拆分, 动态拆分, 块转数字, 数字转块, 简体顺序, 繁体顺序)
     }
 
-    fn 转编码(&self, code: 编码) -> String {
+    pub fn 转编码(&self, code: 编码) -> String {
         code.iter()
             .filter_map(|x| self.棱镜.数字转键.get(&(*x as u64)))
             .cloned()
@@ -639,14 +655,18 @@ impl 冰雪清韵上下文 {
         (category, length, orders)
     }
 
-    pub fn 生成码表(&self, 编码结果: &[冰雪清韵编码信息], 目录: Option<PathBuf>) {
-        let 目录 = 目录.unwrap_or_else(|| PathBuf::from("output"));
-        let mut 宇浩测评码表 = Vec::new();
-        let mut 大竹码表 = Vec::new();
-        let mut 形码盒子测评码表 = Vec::new();
+    /// 没有导出配置时生成的文件
+    pub const 默认导出器: &[&str] = &["测评", "大竹", "形码盒子", "固态词典", "rime"];
+
+    /// 把编码结果整理成各导出器共用的码表
+    pub fn 生成导出码表(&self, 编码结果: &[冰雪清韵编码信息]) -> 导出码表 {
+        let mut 条目列表 = Vec::new();
+        let mut 繁体条目列表 = Vec::new();
         let mut 未排序固态词典码表 = FxHashMap::default();
+        let mut 未排序繁体固态词典码表 = vec![];
         let mut 已占据编码 = FxHashSet::default();
         let mut 当前最短码长 = FxHashMap::default();
+        let 权重 = |频率: 频率| (频率 * 1e8).round() as u64;
         for (可编码对象, 编码信息) in self
             .固定拆分
             .iter()
@@ -658,14 +678,25 @@ impl 冰雪清韵上下文 {
                 已占据编码.insert(全码.clone());
             }
             let 无空格全码 = 全码.replace("_", "");
-            let 带空格全码 = 全码.replace("_", " ");
             let 简码 = self.转编码(编码信息.简体简码);
             当前最短码长.insert(可编码对象.词, 简码.len());
             let 无空格简码 = 简码.replace("_", "");
-            let 带空格简码 = 简码.replace("_", " ");
-            宇浩测评码表.push((可编码对象.词.to_string(), 无空格全码.clone()));
-            大竹码表.push((全码.clone(), 可编码对象.词.to_string()));
-            形码盒子测评码表.push((可编码对象.词, 带空格全码.clone()));
+            条目列表.push(导出条目 {
+                词: 可编码对象.词.to_string(),
+                编码: 全码.clone(),
+                权重: 权重(编码信息.简体频率),
+                类型: 条目类型::全码,
+            });
+            繁体条目列表.push(导出条目 {
+                词: 可编码对象.词.to_string(),
+                编码: 全码.clone(),
+                权重: 权重(编码信息.繁体频率),
+                类型: 条目类型::全码,
+            });
+            if 可编码对象.国字常用 || 可编码对象.陆标 {
+                let 项 = (编码信息.繁体频序, 可编码对象.词, 全码.clone(), 简码.clone());
+                未排序繁体固态词典码表.push(项);
+            }
             if 可编码对象.gb2312 {
                 未排序固态词典码表
                     .entry(无空格全码.clone())
@@ -673,10 +704,21 @@ impl 冰雪清韵上下文 {
                     .push(可编码对象.词.to_string());
             }
             if !无空格简码.is_empty() && 无空格简码 != 无空格全码 {
-                宇浩测评码表.push((可编码对象.词.to_string(), 无空格简码.clone()));
-                大竹码表.push((简码.clone(), 可编码对象.词.to_string()));
-                形码盒子测评码表.push((可编码对象.词, 带空格简码.clone()));
-                if 可编码对象.gb2312 && self.转编码(编码信息.简体简码).len() > 1 {
+                // 简码排在同编码的全码之前，因此权重加倍
+                条目列表.push(导出条目 {
+                    词: 可编码对象.词.to_string(),
+                    编码: 简码.clone(),
+                    权重: 权重(编码信息.简体频率) * 2 + 1,
+                    类型: 条目类型::简码,
+                });
+                // 繁体方案和简体方案共用同一套简码
+                繁体条目列表.push(导出条目 {
+                    词: 可编码对象.词.to_string(),
+                    编码: 简码.clone(),
+                    权重: 权重(编码信息.繁体频率) * 2 + 1,
+                    类型: 条目类型::简码,
+                });
+                if 可编码对象.gb2312 && 简码.len() > 1 {
                     未排序固态词典码表
                         .entry(无空格简码.clone())
                         .or_insert_with(Vec::new)
@@ -684,15 +726,9 @@ impl 冰雪清韵上下文 {
                 }
             }
         }
-        let 拆分结果: Vec<(String, String)> =
-            读取文本文件(PathBuf::from("data/拆分结果.txt"));
-        for (字, 拆分) in 拆分结果 {
-            大竹码表.push((format!("拆分［{}］", 拆分.clone()), 字));
-        }
         self.后处理固态词典码表(
             &mut 未排序固态词典码表,
-            &mut 大竹码表,
-            &mut 宇浩测评码表,
+            &mut 条目列表,
             &mut 已占据编码,
             &当前最短码长,
         );
@@ -704,16 +740,75 @@ impl 冰雪清韵上下文 {
         let re6 = Regex::new(r"^[bpmfdtnlgkhjqxzcsrvwy]{3}[aoeiu;,./]$").unwrap();
         let re7 = Regex::new(r"^[bpmfdtnlgkhjqxzcsrvwy]{4}$").unwrap();
         let res = vec![re1, re2, re3, re4, re5, re6, re7];
-        let 固态词典码表: Vec<_> = 未排序固态词典码表
+        let 固态词典 = 未排序固态词典码表
             .into_iter()
             .sorted_by_key(|(编码字符串, _)| self.排序编码(&res, &编码字符串))
-            .map(|(字符串, 词列表)| (字符串, 词列表.join(" ")))
             .collect();
-        大竹码表.sort_by_key(|(code, _)| self.排序编码(&res, code));
-        写入文本文件(目录.join("冰雪清韵.txt"), 宇浩测评码表);
-        写入文本文件(目录.join("大竹码表.txt"), 大竹码表);
-        写入文本文件(目录.join("形码盒子测评码表.txt"), 形码盒子测评码表);
-        写入文本文件(目录.join("snow_qingyun.fixed.txt"), 固态词典码表);
+        let 繁体固态词典 = self
+            .生成繁体固态词典(未排序繁体固态词典码表)
+            .into_iter()
+            .sorted_by_key(|(编码字符串, _)| self.排序编码(&res, &编码字符串))
+            .collect();
+        let 拆分列表: Vec<(String, String)> =
+            读取文本文件(PathBuf::from("data/拆分结果.txt"));
+        // 大竹码表的编码和拆分一起按编码排序，拆分排在所有编码之后
+        let 大竹码表 = 条目列表
+            .iter()
+            .map(|x| (x.编码.clone(), x.词.clone()))
+            .chain(
+                拆分列表
+                    .iter()
+                    .map(|(字, 拆分)| (format!("拆分［{拆分}］"), 字.clone())),
+            )
+            .sorted_by_key(|(编码, _)| self.排序编码(&res, 编码))
+            .collect();
+        导出码表 {
+            名称: "冰雪清韵".to_string(),
+            标识: "snow_qingyun".to_string(),
+            条目列表,
+            繁体条目列表,
+            固态词典,
+            繁体固态词典,
+            拆分列表,
+            大竹码表,
+            ..Default::default()
+        }
+    }
+
+    /// 繁体常用字按繁体频序排列的全码和简码，加上数字和字母；简词只在简体固态词典中
+    fn 生成繁体固态词典(
+        &self,
+        mut 字列表: Vec<(频序, char, String, String)>,
+    ) -> FxHashMap<String, Vec<String>> {
+        字列表.sort_by_key(|(频序, ..)| *频序);
+        let mut 固态词典码表: FxHashMap<String, Vec<String>> = FxHashMap::default();
+        for (_, 字, 全码, 简码) in 字列表 {
+            let 无空格全码 = 全码.replace("_", "");
+            let 无空格简码 = 简码.replace("_", "");
+            固态词典码表
+                .entry(无空格全码.clone())
+                .or_default()
+                .push(字.to_string());
+            if !无空格简码.is_empty() && 无空格简码 != 无空格全码 && 简码.len() > 1
+            {
+                固态词典码表
+                    .entry(无空格简码)
+                    .or_default()
+                    .push(字.to_string());
+            }
+        }
+        self.添加固态词典符号(&mut 固态词典码表);
+        固态词典码表
+    }
+
+    pub fn 生成码表(
+        &self,
+        编码结果: &[冰雪清韵编码信息],
+        目录: Option<PathBuf>,
+        导出器列表: &[String],
+    ) -> std::io::Result<()> {
+        let 目录 = 目录.unwrap_or_else(|| PathBuf::from("output"));
+        导出(&self.生成导出码表(编码结果), 导出器列表, &目录)
     }
 
     fn 读取简词(
@@ -766,25 +861,85 @@ impl 冰雪清韵上下文 {
         (简词编码列表, 简词编码排序)
     }
 
+    /// 按收益从高到低，每个简词只尝试由读音得到的编码，被占据就放弃；返回编码到候选序号的映射
+    fn 贪心分配简词(
+        候选简词: &[(String, String, i64)],
+        已占据编码: &FxHashSet<String>,
+    ) -> FxHashMap<String, usize> {
+        let mut 分配 = FxHashMap::default();
+        for (序号, (_, 编码, _)) in 候选简词
+            .iter()
+            .enumerate()
+            .sorted_by_key(|(_, (_, _, 收益))| Reverse(*收益))
+        {
+            if 已占据编码.contains(编码) || 分配.contains_key(编码) {
+                continue;
+            }
+            分配.insert(编码.clone(), 序号);
+        }
+        分配
+    }
+
+    /// 把二简词分配看作带权二分图匹配：每个简词可以使用与读音编码前两码相同的任意第三码，
+    /// 权重为词频乘以节省的键数。前两码不同的简词互不竞争，因此按前两码分组分别求最大权匹配。
+    /// 收益相同时优先使用读音编码，其次是当量较小的编码。
+    fn 最优分配简词(
+        候选简词: &[(String, String, i64)],
+        简词编码排序: &FxHashMap<(char, char), Vec<String>>,
+        已占据编码: &FxHashSet<String>,
+    ) -> FxHashMap<String, usize> {
+        let mut 分组: FxHashMap<(char, char), Vec<usize>> = FxHashMap::default();
+        for (序号, (_, 编码, _)) in 候选简词.iter().enumerate() {
+            let mut 按键 = 编码.chars();
+            let 前两码 = (按键.next().unwrap(), 按键.next().unwrap());
+            分组.entry(前两码).or_default().push(序号);
+        }
+        let mut 分配 = FxHashMap::default();
+        for (前两码, 简词序号列表) in 分组 {
+            let mut 候选编码 = 简词编码排序.get(&前两码).cloned().unwrap_or_default();
+            for &序号 in &简词序号列表 {
+                if !候选编码.contains(&候选简词[序号].1) {
+                    候选编码.push(候选简词[序号].1.clone());
+                }
+            }
+            let 编码数 = 候选编码.len() as i64;
+            let 可用编码: Vec<(usize, String)> = 候选编码
+                .into_iter()
+                .enumerate()
+                .filter(|(_, x)| !已占据编码.contains(x))
+                .collect();
+            let 列数 = 简词序号列表.len().max(可用编码.len());
+            let 权重: Vec<Vec<i64>> = 可用编码
+                .iter()
+                .map(|(排名, 编码)| {
+                    let mut 行 = vec![0; 列数];
+                    for (列, &序号) in 简词序号列表.iter().enumerate() {
+                        let (_, 读音编码, 收益) = &候选简词[序号];
+                        let 读音偏好 = if 编码 == 读音编码 { 编码数 } else { 0 };
+                        let 偏好 = 读音偏好 + 编码数 - *排名 as i64;
+                        行[列] = 收益 * (编码数 * 2 + 1) + 偏好;
+                    }
+                    行
+                })
+                .collect();
+            for (行, 列) in 最大权匹配(&权重).into_iter().enumerate() {
+                if 列 < 简词序号列表.len() {
+                    分配.insert(可用编码[行].1.clone(), 简词序号列表[列]);
+                }
+            }
+        }
+        分配
+    }
+
     pub fn 后处理固态词典码表(
         &self,
         固态词典码表: &mut FxHashMap<String, Vec<String>>,
-        大竹码表: &mut Vec<(String, String)>,
-        宇浩测评码表: &mut Vec<(String, String)>,
+        条目列表: &mut Vec<导出条目>,
         已占据编码: &mut FxHashSet<String>,
         当前最短码长: &FxHashMap<char, usize>,
     ) {
         let 简码覆盖: 简码覆盖 = from_str(&read_to_string("data/override.yaml").unwrap()).unwrap();
-        let (mut 简词编码列表, _) = self.读取简词();
-        简词编码列表.sort_by_key(|(简词, _, 词频)| {
-            let chars: Vec<_> = 简词.chars().collect();
-            let total_length = 当前最短码长[&chars[0]] + 当前最短码长[&chars[1]];
-            Reverse((total_length as i64 - 3) * (*词频 as i64))
-        });
-        // let 词频映射: FxHashMap<String, u64> = 简词编码列表
-        //     .iter()
-        //     .map(|(简词, _, 词频)| (简词.clone(), *词频))
-        //     .collect();
+        let (简词编码列表, 简词编码排序) = self.读取简词();
         for (简词, 编码) in 简码覆盖.简词快符.clone() {
             固态词典码表.insert(编码.clone(), vec![简词.clone()]);
         }
@@ -794,15 +949,14 @@ impl 冰雪清韵上下文 {
             简词映射.insert(编码.clone(), 词.clone());
             已占据编码.insert(编码.clone());
         }
-        let mut 未编码简词列表 = vec![];
-        let mut 自动简词 = 0;
-        for (简词, 编码, _) in 简词编码列表.iter() {
+        let mut 候选简词 = vec![];
+        for (简词, 编码, 词频) in 简词编码列表 {
             // 跳过一简词
-            if 简码覆盖.简词快符.iter().any(|(s, _)| s == 简词) {
+            if 简码覆盖.简词快符.iter().any(|(s, _)| *s == 简词) {
                 continue;
             }
             // 跳过已手动编码的二简词
-            if 简码覆盖.二简词.contains_key(简词) {
+            if 简码覆盖.二简词.contains_key(&简词) {
                 continue;
             }
             let chars: Vec<_> = 简词.chars().collect();
@@ -814,44 +968,77 @@ impl 冰雪清韵上下文 {
                 );
                 continue;
             }
-            if 已占据编码.contains(编码) {
-                未编码简词列表.push((简词, 编码));
-                continue;
-            }
-            自动简词 += 1;
-            简词映射.insert(编码.clone(), 简词.clone());
+            let 收益 = (total_length as i64 - 3) * 词频 as i64;
+            候选简词.push((简词, 编码, 收益));
+        }
+        let 贪心分配 = Self::贪心分配简词(&候选简词, 已占据编码);
+        let 最优分配 = Self::最优分配简词(&候选简词, &简词编码排序, 已占据编码);
+        let 总收益 =
+            |分配: &FxHashMap<String, usize>| 分配.values().map(|x| 候选简词[*x].2).sum::<i64>();
+        let (贪心收益, 最优收益) = (总收益(&贪心分配), 总收益(&最优分配));
+        let 替代编码数 = 最优分配
+            .iter()
+            .filter(|(编码, 序号)| **编码 != 候选简词[**序号].1)
+            .count();
+        println!(
+            "二简词分配：贪心 {} 个，收益 {}；最优匹配 {} 个，收益 {}，提升 {:.2}%，其中 {} 个使用了替代编码",
+            贪心分配.len(),
+            贪心收益,
+            最优分配.len(),
+            最优收益,
+            (最优收益 - 贪心收益) as f64 / 贪心收益.max(1) as f64 * 100.0,
+            替代编码数
+        );
+        let 已分配: FxHashSet<usize> = 最优分配.values().cloned().collect();
+        for (编码, 序号) in 最优分配.iter() {
+            简词映射.insert(编码.clone(), 候选简词[*序号].0.clone());
             已占据编码.insert(编码.clone());
         }
         println!(
             "二简词编码分配完成：手动 {} 个，自动 {} 个，共 {} 个",
             简码覆盖.二简词.len(),
-            自动简词,
-            简码覆盖.二简词.len() + 自动简词
-        );
-        println!(
-            "未能分配编码的二简词前 100：{:?}",
-            未编码简词列表.iter().take(100).collect::<Vec<_>>()
+            最优分配.len(),
+            简码覆盖.二简词.len() + 最优分配.len()
         );
+        let 未编码简词列表: Vec<_> = 候选简词
+            .iter()
+            .enumerate()
+            .filter(|(序号, _)| !已分配.contains(序号))
+            .sorted_by_key(|(_, (_, _, 收益))| Reverse(*收益))
+            .map(|(_, (简词, 编码, _))| (简词, 编码))
+            .take(100)
+            .collect();
+        println!("未能分配编码的二简词前 100：{:?}", 未编码简词列表);
         for (编码, 简词) in 简词映射.clone() {
             固态词典码表
                 .entry(编码.clone())
                 .or_insert_with(Vec::new)
                 .insert(0, 简词.clone());
         }
-        let mut 大竹码表增加 = vec![];
-        let mut 宇浩码表增加 = vec![];
+        let mut 简词条目 = vec![];
         for (简词, 编码) in 简码覆盖.简词快符 {
-            大竹码表增加.push((编码.clone(), 简词.clone()));
-            宇浩码表增加.push((简词.clone(), 编码.clone()));
+            简词条目.push(导出条目 {
+                词: 简词,
+                编码,
+                权重: 0,
+                类型: 条目类型::简词,
+            });
         }
         for (编码, 简词) in 简词映射 {
-            大竹码表增加.push((编码.clone(), 简词.clone()));
-            宇浩码表增加.push((简词.clone(), 编码.clone()));
+            简词条目.push(导出条目 {
+                词: 简词,
+                编码,
+                权重: 0,
+                类型: 条目类型::简词,
+            });
         }
-        大竹码表增加.sort_by_key(|(code, _)| self.排序编码(&vec![], code));
-        宇浩码表增加.sort_by_key(|(_, code)| self.排序编码(&vec![], code));
-        大竹码表.splice(0..0, 大竹码表增加);
-        宇浩测评码表.splice(0..0, 宇浩码表增加);
+        简词条目.sort_by_key(|x| self.排序编码(&vec![], &x.编码));
+        条目列表.splice(0..0, 简词条目);
+        self.添加固态词典符号(固态词典码表);
+    }
+
+    /// 固态词典中的数字和字母
+    fn 添加固态词典符号(&self, 固态词典码表: &mut FxHashMap<String, Vec<String>>) {
         let 数字信息 = [
             ('1', '一', "yi"),
             ('2', '二', "vi"),
@@ -914,145 +1101,118 @@ impl 冰雪清韵上下文 {
     }
 
     // 分析前 3000 字中全码重码和简码差指法的情况
-    pub fn 分析码表(
+    pub fn 生成分析报告(
         &self,
         编码结果: &[冰雪清韵编码信息],
-        目录: Option<PathBuf>,
-    ) -> Result<(), 错误> {
-        let 分析路径 = 目录
-            .unwrap_or_else(|| PathBuf::from("output"))
-            .join("分析.md");
-        let mut 文件 = File::create(分析路径).unwrap();
-        let mut 二码字根字 = FxHashMap::default();
-        let mut 三码字根字 = FxHashMap::default();
-        let mut 无理一简多重 = FxHashMap::default();
-        let mut 二简 = FxHashMap::default();
+        截断: 分析截断,
+    ) -> 分析报告 {
+        let mut 二码字根字 = vec![];
+        let mut 三码字根字 = vec![];
+        let mut 无理一简多重 = vec![];
+        let mut 二简 = vec![];
+        let 条目 = |序号: usize, 编码: String| 分析条目 {
+            字: self.固定拆分[序号].词,
+            编码,
+            频率: 编码结果[序号].简体频率 as f64 * 10000.0,
+            频序: 序号,
+        };
         for (序号, 编码信息) in 编码结果.iter().enumerate() {
-            let 词 = self.固定拆分[序号].词;
-            let 频率 = 编码信息.简体频率 * 10000.0;
             if !self.固定拆分[序号].通规 {
                 continue;
             }
             if 编码信息.字根字 {
                 let 全码 = self.转编码(编码信息.全码);
-                let 第一码 = 全码.chars().next().unwrap();
                 if 编码信息.全码 == 编码信息.简体简码 {
-                    二码字根字
-                        .entry(第一码)
-                        .or_insert_with(Vec::new)
-                        .push(format!("{词} {全码} {频率:.0}"));
+                    二码字根字.push(条目(序号, 全码));
                 } else {
-                    三码字根字
-                        .entry(第一码)
-                        .or_insert_with(Vec::new)
-                        .push(format!("{词} {全码} {频率:.0}"));
+                    三码字根字.push(条目(序号, 全码));
                 }
             } else {
                 let 简码 = self.转编码(编码信息.简体简码);
                 if 简码.len() == 2 {
-                    let 第一码 = 简码.chars().next().unwrap();
-                    无理一简多重
-                        .entry(第一码)
-                        .or_insert_with(Vec::new)
-                        .push(format!("{词} {简码} {频率:.0}"));
+                    无理一简多重.push(条目(序号, 简码));
                 } else if 简码.len() == 3 && 简码.ends_with("_") {
-                    let 第一码 = 简码.chars().next().unwrap();
-                    二简
-                        .entry(第一码)
-                        .or_insert_with(Vec::new)
-                        .push(format!("{词} {简码} {频率:.0}"));
+                    二简.push(条目(序号, 简码));
                 }
             }
         }
-        writeln!(
-            文件,
-            "# 无理一简多重 {}\n",
-            无理一简多重.values().map(|x| x.len()).sum::<usize>()
-        )?;
-        for (第一码, 列表) in 无理一简多重.iter().sorted_by_key(|x| x.0) {
-            writeln!(文件, "- {第一码}: {}", 列表.join(" "))?;
-        }
-        writeln!(
-            文件,
-            "\n# 二码字根字 {}\n",
-            二码字根字.values().map(|x| x.len()).sum::<usize>()
-        )?;
-        for (第一码, 列表) in 二码字根字.iter().sorted_by_key(|x| x.0) {
-            writeln!(文件, "- {第一码}: {}", 列表.join(" "))?;
-        }
-        writeln!(
-            文件,
-            "\n# 二简 {}\n",
-            二简.values().map(|x| x.len()).sum::<usize>()
-        )?;
-        for (第一码, 列表) in 二简.iter().sorted_by_key(|x| x.0) {
-            writeln!(文件, "- {第一码}: {}", 列表.join(" "))?;
-        }
-        writeln!(
-            文件,
-            "\n# 三码字根字 {}\n",
-            三码字根字.values().map(|x| x.len()).sum::<usize>()
-        )?;
-        for (第一码, 列表) in 三码字根字.iter().sorted_by_key(|x| x.0) {
-            writeln!(文件, "- {第一码}: {}", 列表.join(" "))?;
+        for 列表 in [
+            &mut 二码字根字,
+            &mut 三码字根字,
+            &mut 无理一简多重,
+            &mut 二简,
+        ] {
+            列表.sort_by(|a, b| a.编码.cmp(&b.编码));
         }
-        let 简体前三千: Vec<_> = self.简体顺序.iter().take(3000).cloned().collect();
-        let 繁体前三千: Vec<_> = self.繁体顺序.iter().take(3000).cloned().collect();
-        let 通打前三千: Vec<_> = (0..3000).collect();
+        let 简体前若干: Vec<_> = self.简体顺序.iter().take(截断.重码).cloned().collect();
+        let 繁体前若干: Vec<_> = self.繁体顺序.iter().take(截断.重码).cloned().collect();
+        let 通打前若干: Vec<_> = (0..截断.重码.min(编码结果.len())).collect();
         let 指法标记 = 指法标记::new();
         let mut 差指法 = vec![];
         let mut 四键字 = vec![];
         let mut 三键字 = vec![];
-        for &序号 in 简体前三千.iter() {
-            let 编码信息 = &编码结果[序号];
-            let 词 = self.固定拆分[序号].词;
-            let 简码 = self.转编码(编码信息.简体简码);
-            if 简码.len() == 3 && 序号 < 200 {
-                三键字.push((词, 简码.clone(), 编码信息.简体频率));
+        for &序号 in 简体前若干.iter() {
+            let 简码 = self.转编码(编码结果[序号].简体简码);
+            if 简码.len() == 3 {
+                三键字.push(条目(序号, 简码.clone()));
             }
-            if 简码.len() == 4 && 序号 < 500 {
-                四键字.push((词, 简码.clone(), 编码信息.简体频率));
+            if 简码.len() == 4 {
+                四键字.push(条目(序号, 简码.clone()));
             }
-            if 序号 < 1500 {
-                let 简码: Vec<char> = 简码.chars().collect();
-                for 键索引 in 0..(简码.len() - 1) {
-                    let 组合 = (简码[键索引], 简码[键索引 + 1]);
-                    if 指法标记.同指大跨排.contains(&组合) || 指法标记.错手.contains(&组合)
-                    {
-                        差指法.push((词, 简码.iter().collect::<String>()));
-                        break;
-                    }
+            let 按键: Vec<char> = 简码.chars().collect();
+            for 组合 in 按键.windows(2) {
+                let 组合 = (组合[0], 组合[1]);
+                if 指法标记.同指大跨排.contains(&组合) || 指法标记.错手.contains(&组合)
+                {
+                    差指法.push(条目(序号, 简码));
+                    break;
                 }
             }
         }
-        writeln!(文件, "\n# 前 1500 中简码差指法项\n")?;
-        for (字, 编码) in 差指法 {
-            write!(文件, "{字} {编码}；")?;
-        }
-        writeln!(文件, "\n\n# 前 200 中三键字\n").unwrap();
-        for (字, 编码, 频率) in 三键字 {
-            write!(文件, "{字} {编码} {:.0}；", 频率 * 10000.0)?;
+        let mut 全码重码 = IndexMap::new();
+        let 频率函数: [fn(&冰雪清韵编码信息) -> 频率; 3] =
+            [|x| x.简体频率, |x| x.繁体频率, |x| x.通打频率];
+        for ((名称, 顺序), 频率) in [
+            ("简体", 简体前若干),
+            ("繁体", 繁体前若干),
+            ("通打", 通打前若干),
+        ]
+        .into_iter()
+        .zip(频率函数)
+        {
+            let 重码组列表 = self
+                .翻转码表(编码结果, &顺序, &频率)
+                .into_iter()
+                .map(|(全码, 字, 次选频率)| 重码组 {
+                    全码: self.转编码(全码),
+                    字,
+                    次选频率: 次选频率 as f64 * 1_000_000.0,
+                })
+                .collect();
+            全码重码.insert(名称.to_string(), 重码组列表);
         }
-        writeln!(文件, "\n\n# 前 500 中四键字\n").unwrap();
-        for (字, 编码, 频率) in 四键字 {
-            write!(文件, "{字} {编码} {:.0}；", 频率 * 10000.0)?;
-        }
-        writeln!(文件, "")?;
-        let 简体重码组列表 = self.翻转码表(编码结果, &简体前三千, &|x| x.简体频率);
-        let 繁体重码组列表 = self.翻转码表(编码结果, &繁体前三千, &|x| x.繁体频率);
-        let 通打重码组列表 = self.翻转码表(编码结果, &通打前三千, &|x| x.通打频率);
-        for (label, 重码组列表) in [
-            ("简体", 简体重码组列表),
-            ("繁体", 繁体重码组列表),
-            ("通打", 通打重码组列表),
-        ] {
-            writeln!(文件, "\n# 前 3000 中{label}全码重码\n")?;
-            for (全码, 重码组, 次选频率) in 重码组列表 {
-                let 全码 = self.转编码(全码);
-                let 百万分之频率 = 次选频率 * 1_000_000.0;
-                writeln!(文件, "- {全码} {重码组:?} [{百万分之频率:.2} μ]")?;
-            }
+        分析报告 {
+            截断,
+            无理一简多重,
+            二码字根字,
+            二简,
+            三码字根字,
+            差指法,
+            三键字,
+            四键字,
+            全码重码,
         }
+    }
+
+    /// 写出 `分析.json` 和 `分析.html`
+    pub fn 分析码表(
+        &self,
+        编码结果: &[冰雪清韵编码信息],
+        目录: Option<PathBuf>,
+    ) -> Result<(), 错误> {
+        let 目录 = 目录.unwrap_or_else(|| PathBuf::from("output"));
+        let 报告 = self.生成分析报告(编码结果, 分析截断::读取()?);
+        报告.写入(&目录)?;
         Ok(())
     }
 }
@@ -0,0 +1,255 @@
+//! 比较两个冰雪清韵决策。
+//!
+//! 两个决策在同一个上下文中分别编码后，按字根和按字列出变化，并用通打频率加权，估计从一个方案迁移到另一个方案的重学代价。
+
+use crate::qingyun::{
+    context::冰雪清韵上下文, encoder::冰雪清韵编码器, 元素安排, 冰雪清韵决策, 冰雪清韵编码信息,
+    编码, 频率,
+};
+use chai::{元素, 棱镜};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{fmt::Display, fs::File, io::Write, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct 字根变化 {
+    pub 字根: String,
+    pub 原安排: String,
+    pub 新安排: String,
+    /// 当前拆分中用到这个字根且全码改变的字数
+    pub 影响字数: usize,
+    pub 影响频率: 频率,
+}
+
+#[derive(Debug, Clone)]
+pub struct 字变化 {
+    pub 字: char,
+    pub 原全码: String,
+    pub 新全码: String,
+    pub 原简码: String,
+    pub 新简码: String,
+    pub 频率: 频率,
+}
+
+#[derive(Debug, Clone)]
+pub struct 重码组变化 {
+    pub 全码: String,
+    pub 字: Vec<char>,
+    pub 次选频率: 频率,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct 差异报告 {
+    /// 补码键、第一主根、第二主根的变化
+    pub 全局变化: Vec<(String, char, char)>,
+    pub 字根变化: Vec<字根变化>,
+    pub 字变化: Vec<字变化>,
+    pub 新增重码组: Vec<重码组变化>,
+    pub 消失重码组: Vec<重码组变化>,
+    /// 全码改变的字的频率之和
+    pub 全码重学代价: 频率,
+    /// 实际打法（简码）改变的字的频率之和
+    pub 简码重学代价: 频率,
+}
+
+fn 描述安排(安排: &元素安排, 棱镜: &棱镜) -> String {
+    let 名称 = |x: &元素| 棱镜.数字转元素[x].clone();
+    match 安排 {
+        元素安排::未选取 => "未选取".to_string(),
+        元素安排::键位(键) => 键.to_string(),
+        元素安排::归并(元素) => format!("归并到 {}", 名称(元素)),
+        元素安排::键位第一(键) => format!("{键}（第一主根小码）"),
+        元素安排::键位第二(键) => format!("{键}（第二主根小码）"),
+        元素安排::归并韵母 { 字根, 韵母 } => format!("{} + {}", 名称(字根), 名称(韵母)),
+        元素安排::声母韵母 { 声母, 韵母 } => format!("{} + {}", 名称(声母), 名称(韵母)),
+    }
+}
+
+fn 重码组(
+    上下文: &冰雪清韵上下文,
+    编码结果: &[冰雪清韵编码信息],
+) -> FxHashMap<(编码, Vec<char>), 频率> {
+    let 顺序: Vec<_> = (0..编码结果.len()).collect();
+    上下文
+        .翻转码表(编码结果, &顺序, &|x| x.通打频率)
+        .into_iter()
+        .map(|(全码, 字, 次选频率)| ((全码, 字), 次选频率))
+        .collect()
+}
+
+impl 差异报告 {
+    /// 比较同一上下文中的两个决策，编码器需已对各自的决策完成编码
+    pub fn 新建(
+        上下文: &冰雪清韵上下文,
+        甲: &冰雪清韵决策,
+        编码器甲: &冰雪清韵编码器,
+        乙: &冰雪清韵决策,
+        编码器乙: &冰雪清韵编码器,
+    ) -> Self {
+        let 棱镜 = &上下文.棱镜;
+        let mut 报告 = 差异报告::default();
+        for (名称, 原, 新) in [
+            ("补码键", 甲.补码键, 乙.补码键),
+            ("第一主根", 甲.第一主根, 乙.第一主根),
+            ("第二主根", 甲.第二主根, 乙.第二主根),
+        ] {
+            if 原 != 新 {
+                报告.全局变化.push((名称.to_string(), 原, 新));
+            }
+        }
+        let 转编码 = |x| 上下文.转编码(x);
+        let mut 全码改变 = FxHashSet::default();
+        for (序号, (原, 新)) in 编码器甲.编码结果.iter().zip(&编码器乙.编码结果).enumerate()
+        {
+            let 频率 = 原.通打频率;
+            let 全码改变了 = 原.计重全码 != 新.计重全码;
+            let 简码改变了 = 原.简体简码 != 新.简体简码;
+            if 全码改变了 {
+                全码改变.insert(序号);
+                报告.全码重学代价 += 频率;
+            }
+            if 简码改变了 {
+                报告.简码重学代价 += 频率;
+            }
+            if 全码改变了 || 简码改变了 {
+                报告.字变化.push(字变化 {
+                    字: 上下文.固定拆分[序号].词,
+                    原全码: 转编码(原.计重全码),
+                    新全码: 转编码(新.计重全码),
+                    原简码: 转编码(原.简体简码),
+                    新简码: 转编码(新.简体简码),
+                    频率,
+                });
+            }
+        }
+        报告
+            .字变化
+            .sort_by(|a, b| b.频率.partial_cmp(&a.频率).unwrap());
+        // 字根影响的字按两边实际采用的拆分统计
+        let mut 字根用字: FxHashMap<元素, FxHashSet<usize>> = FxHashMap::default();
+        for 编码器 in [编码器甲, 编码器乙] {
+            for (序号, 序列) in 编码器.拆分序列.iter().enumerate() {
+                for &元素 in 序列.iter().filter(|x| **x != 0) {
+                    字根用字.entry(元素).or_default().insert(序号);
+                }
+            }
+        }
+        for (元素, (原, 新)) in 甲.元素.iter().zip(&乙.元素).enumerate() {
+            if 原 == 新 || !棱镜.数字转元素.contains_key(&元素) {
+                continue;
+            }
+            let 影响: Vec<_> = 字根用字
+                .get(&元素)
+                .map(|x| x.iter().filter(|序号| 全码改变.contains(*序号)).collect())
+                .unwrap_or_default();
+            报告.字根变化.push(字根变化 {
+                字根: 棱镜.数字转元素[&元素].clone(),
+                原安排: 描述安排(原, 棱镜),
+                新安排: 描述安排(新, 棱镜),
+                影响字数: 影响.len(),
+                影响频率: 影响
+                    .iter()
+                    .map(|序号| 编码器甲.编码结果[**序号].通打频率)
+                    .sum(),
+            });
+        }
+        报告
+            .字根变化
+            .sort_by(|a, b| b.影响频率.partial_cmp(&a.影响频率).unwrap());
+        let 原重码组 = 重码组(上下文, &编码器甲.编码结果);
+        let 新重码组 = 重码组(上下文, &编码器乙.编码结果);
+        for (来源, 对照, 目标) in [
+            (&新重码组, &原重码组, &mut 报告.新增重码组),
+            (&原重码组, &新重码组, &mut 报告.消失重码组),
+        ] {
+            for ((全码, 字), 次选频率) in 来源
+                .iter()
+                .filter(|(x, _)| !对照.contains_key(*x))
+                .sorted_by(|a, b| b.1.partial_cmp(a.1).unwrap())
+            {
+                目标.push(重码组变化 {
+                    全码: 转编码(*全码),
+                    字: 字.clone(),
+                    次选频率: *次选频率,
+                });
+            }
+        }
+        报告
+    }
+
+    pub fn 写入(&self, 路径: &PathBuf) -> std::io::Result<()> {
+        let mut 文件 = File::create(路径)?;
+        write!(文件, "{self}")
+    }
+}
+
+impl Display for 差异报告 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# 总计\n")?;
+        writeln!(
+            f,
+            "- 全码改变 {} 字，重学代价 {:.2}%",
+            self.字变化.iter().filter(|x| x.原全码 != x.新全码).count(),
+            self.全码重学代价 * 100.0
+        )?;
+        writeln!(
+            f,
+            "- 简码改变 {} 字，重学代价 {:.2}%",
+            self.字变化.iter().filter(|x| x.原简码 != x.新简码).count(),
+            self.简码重学代价 * 100.0
+        )?;
+        writeln!(
+            f,
+            "- 新增重码组 {} 个，消失重码组 {} 个",
+            self.新增重码组.len(),
+            self.消失重码组.len()
+        )?;
+        if !self.全局变化.is_empty() {
+            writeln!(f, "\n# 全局变化\n")?;
+            for (名称, 原, 新) in &self.全局变化 {
+                writeln!(f, "- {名称}：{原} → {新}")?;
+            }
+        }
+        writeln!(f, "\n# 字根变化 {}\n", self.字根变化.len())?;
+        for 变化 in &self.字根变化 {
+            writeln!(
+                f,
+                "- {}：{} → {}，影响 {} 字 [{:.2} ‱]",
+                变化.字根,
+                变化.原安排,
+                变化.新安排,
+                变化.影响字数,
+                变化.影响频率 * 10000.0
+            )?;
+        }
+        writeln!(f, "\n# 字变化 {}\n", self.字变化.len())?;
+        for 变化 in &self.字变化 {
+            writeln!(
+                f,
+                "- {} 全码 {} → {}，简码 {} → {} [{:.2} ‱]",
+                变化.字,
+                变化.原全码,
+                变化.新全码,
+                变化.原简码,
+                变化.新简码,
+                变化.频率 * 10000.0
+            )?;
+        }
+        for (标题, 列表) in [
+            ("新增重码组", &self.新增重码组),
+            ("消失重码组", &self.消失重码组),
+        ] {
+            writeln!(f, "\n# {标题} {}\n", 列表.len())?;
+            for 变化 in 列表 {
+                writeln!(
+                    f,
+                    "- {} {:?} [{:.2} μ]",
+                    变化.全码,
+                    变化.字,
+                    变化.次选频率 * 1_000_000.0
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
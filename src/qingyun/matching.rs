@@ -0,0 +1,74 @@
+//! 带权二分图的最大权匹配，用于分配二简词的编码。
+
+/// 匈牙利算法（Kuhn-Munkres），返回每一行匹配到的列。
+///
+/// 权重矩阵的行数不能多于列数，每一行都会匹配到一个不同的列；不希望匹配的位置可以把权重设为 0，
+/// 再在调用方丢弃这些匹配。
+pub fn 最大权匹配(权重: &[Vec<i64>]) -> Vec<usize> {
+    let 行数 = 权重.len();
+    if 行数 == 0 {
+        return vec![];
+    }
+    let 列数 = 权重[0].len();
+    assert!(行数 <= 列数, "行数 {行数} 多于列数 {列数}");
+    // 以下按照求最小代价的形式，下标从 1 开始，0 号列是虚拟的起点
+    let 代价 = |i: usize, j: usize| -权重[i - 1][j - 1];
+    let mut 行势 = vec![0i64; 行数 + 1];
+    let mut 列势 = vec![0i64; 列数 + 1];
+    let mut 列匹配 = vec![0usize; 列数 + 1];
+    let mut 前驱 = vec![0usize; 列数 + 1];
+    for i in 1..=行数 {
+        列匹配[0] = i;
+        let mut 当前列 = 0;
+        let mut 最小松弛 = vec![i64::MAX; 列数 + 1];
+        let mut 已访问 = vec![false; 列数 + 1];
+        loop {
+            已访问[当前列] = true;
+            let 当前行 = 列匹配[当前列];
+            let mut 增量 = i64::MAX;
+            let mut 下一列 = 0;
+            for j in 1..=列数 {
+                if 已访问[j] {
+                    continue;
+                }
+                let 松弛 = 代价(当前行, j) - 行势[当前行] - 列势[j];
+                if 松弛 < 最小松弛[j] {
+                    最小松弛[j] = 松弛;
+                    前驱[j] = 当前列;
+                }
+                if 最小松弛[j] < 增量 {
+                    增量 = 最小松弛[j];
+                    下一列 = j;
+                }
+            }
+            for j in 0..=列数 {
+                if 已访问[j] {
+                    行势[列匹配[j]] += 增量;
+                    列势[j] -= 增量;
+                } else {
+                    最小松弛[j] -= 增量;
+                }
+            }
+            当前列 = 下一列;
+            if 列匹配[当前列] == 0 {
+                break;
+            }
+        }
+        // 沿增广路翻转匹配
+        loop {
+            let 上一列 = 前驱[当前列];
+            列匹配[当前列] = 列匹配[上一列];
+            当前列 = 上一列;
+            if 当前列 == 0 {
+                break;
+            }
+        }
+    }
+    let mut 结果 = vec![0; 行数];
+    for j in 1..=列数 {
+        if 列匹配[j] != 0 {
+            结果[列匹配[j] - 1] = j - 1;
+        }
+    }
+    结果
+}
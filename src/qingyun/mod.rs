@@ -5,10 +5,16 @@ use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::common::转换;
+pub mod adaptive;
+pub mod chart;
+pub mod cli;
 pub mod context;
+pub mod diff;
 pub mod encoder;
+pub mod matching;
 pub mod objective;
 pub mod operators;
+pub mod report;
 
 pub const 大集合: [char; 21] = [
     'b', 'p', 'm', 'f', 'd', 't', 'n', 'l', 'g', 'k', 'h', 'j', 'q', 'x', 'z', 'c', 's', 'r', 'v',
@@ -237,6 +243,7 @@ impl 元素安排 {
     fn from(mapped: &Mapped, 棱镜: &棱镜) -> Self {
         match mapped {
             Mapped::Unused(()) => 元素安排::未选取,
+            Mapped::Basic(键位) => 元素安排::键位(键位.chars().next().unwrap()),
             Mapped::Grouped { element } => 元素安排::归并(棱镜.元素转数字[element]),
             Mapped::Advanced(keys) => {
                 let first = keys[0].clone();
@@ -355,6 +362,13 @@ impl 冰雪清韵决策变化 {
     pub fn 全局变化() -> Self {
         Self::新建(true, vec![], vec![], vec![])
     }
+
+    pub fn 是无变化(&self) -> bool {
+        !self.全局变化
+            && self.移动字根.is_empty()
+            && self.增加字根.is_empty()
+            && self.减少字根.is_empty()
+    }
 }
 
 impl 决策 for 冰雪清韵决策 {
@@ -1,5 +1,5 @@
 use crate::{qingyun::{
-    context::冰雪清韵上下文, encoder::冰雪清韵编码器, 元素安排, 冰雪清韵决策, 冰雪清韵决策变化,
+    adaptive::分数记录, context::冰雪清韵上下文, encoder::冰雪清韵编码器, 元素安排, 冰雪清韵决策, 冰雪清韵决策变化,
     冰雪清韵决策空间, 大集合, 所有汉字数, 转换, 进制, 音节信息, 频序, 频率,
 }};
 use chai::{encoders::编码器, objectives::{metric::键盘布局, 目标函数}, 棱镜, 键位分布信息};
@@ -155,6 +155,7 @@ pub struct 冰雪清韵目标函数 {
     pub 音节熵: f32,
     pub 简体总稳健频率: f32,
     pub 繁体总稳健频率: f32,
+    pub 分数记录: Option<分数记录>,
 }
 
 impl 冰雪清韵目标函数 {
@@ -192,6 +193,7 @@ impl 冰雪清韵目标函数 {
             音节熵: Self::计算音节熵(&上下文.拼音),
             简体总稳健频率,
             繁体总稳健频率,
+            分数记录: None,
         }
     }
 
@@ -440,6 +442,9 @@ impl 目标函数 for 冰雪清韵目标函数 {
     ) -> (冰雪清韵指标, f64) {
         self.编码器.编码(决策, 变化, &mut vec![]);
         let (指标, 目标函数值) = self.calculate(决策);
+        if let Some(分数记录) = &self.分数记录 {
+            分数记录.写入(目标函数值.into());
+        }
         (指标, 目标函数值.into())
     }
 }
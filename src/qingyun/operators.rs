@@ -1,48 +1,124 @@
 use crate::qingyun::{
+    adaptive::{分数记录, 变异算子, 自适应选择器},
     context::冰雪清韵上下文, 不好的大集合键, 主根小码, 元素安排, 冰雪清韵决策, 冰雪清韵决策变化,
     冰雪清韵决策空间, 大集合, 笔画,
 };
 use chai::{operators::变异, 元素, 棱镜};
 use rand::{
-    random, rng,
+    Rng, SeedableRng,
+    rngs::StdRng,
     seq::{IndexedRandom, IteratorRandom},
 };
 use rustc_hash::FxHashMap;
-use std::collections::VecDeque;
+use std::{collections::VecDeque, path::PathBuf};
 
 pub struct 冰雪清韵操作 {
     _棱镜: 棱镜,
     决策空间: 冰雪清韵决策空间,
     下游字根: FxHashMap<元素, Vec<元素>>,
     笔画列表: Vec<元素>,
+    自适应: Option<自适应选择器>,
+    改动: 改动记录,
+    回滚次数: [u64; 7],
+    /// 传播时找不到合法安排的字根及其次数
+    无法传播字根: FxHashMap<元素, u64>,
+    rng: StdRng,
+}
+
+/// 一次变异改写过的元素和它们原来的安排，回滚时只恢复这些元素
+#[derive(Debug, Default)]
+pub struct 改动记录 {
+    原安排: Vec<(元素, 元素安排)>,
+    补码键: char,
+    第一主根: char,
+    第二主根: char,
+}
+
+impl 改动记录 {
+    fn 开始(&mut self, 决策: &冰雪清韵决策) {
+        self.原安排.clear();
+        self.补码键 = 决策.补码键;
+        self.第一主根 = 决策.第一主根;
+        self.第二主根 = 决策.第二主根;
+    }
+
+    fn 设置(&mut self, 决策: &mut 冰雪清韵决策, 元素: 元素, 安排: 元素安排) {
+        self.原安排.push((元素, 决策.元素[元素]));
+        决策.元素[元素] = 安排;
+    }
+
+    fn 元素(&self) -> impl Iterator<Item = 元素> + '_ {
+        self.原安排.iter().map(|(元素, _)| *元素)
+    }
+
+    /// 是否真的改变了某个值；同一个元素改写多次时以最早记录的原安排为准
+    fn 有改动(&self, 决策: &冰雪清韵决策) -> bool {
+        let 元素有改动 = self.原安排.iter().enumerate().any(|(序号, (元素, 安排))| {
+            self.原安排.iter().position(|x| x.0 == *元素) == Some(序号)
+                && 决策.元素[*元素] != *安排
+        });
+        元素有改动
+            || [self.补码键, self.第一主根, self.第二主根]
+                != [决策.补码键, 决策.第一主根, 决策.第二主根]
+    }
+
+    fn 回滚(&self, 决策: &mut 冰雪清韵决策) {
+        for (元素, 安排) in self.原安排.iter().rev() {
+            决策.元素[*元素] = *安排;
+        }
+        决策.补码键 = self.补码键;
+        决策.第一主根 = self.第一主根;
+        决策.第二主根 = self.第二主根;
+    }
 }
 
 impl 变异 for 冰雪清韵操作 {
     type 决策 = 冰雪清韵决策;
     fn 变异(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let 随机数: f64 = random();
-        let mut 变化 = if 随机数 < 0.05 {
-            self.改变补码键(决策)
-        } else if 随机数 < 0.2 {
-            self.交换主副根(决策)
-        } else if 随机数 < 0.25 {
-            self.交换主根(决策)
-        } else if 随机数 < 0.30 {
-            self.移动笔画(决策)
-        } else if 随机数 < 0.55 {
-            self.产生副根(决策)
-        } else if 随机数 < 0.80 {
-            self.湮灭副根(决策)
+        let 随机数: f64 = self.rng.random();
+        let 算子 = if let Some(自适应) = &mut self.自适应 {
+            自适应.结算(决策);
+            自适应.选择(随机数)
         } else {
-            self.移动副根(决策)
+            变异算子::按概率选择(&变异算子::固定概率, 随机数)
+        };
+        // 变异是事务性的：随机移动导致无法传播的状态时，恢复改动过的元素并当作无变化
+        self.改动.开始(决策);
+        let 变化 = match 算子 {
+            变异算子::改变补码键 => Some(self.改变补码键(决策)),
+            变异算子::交换主副根 => self.交换主副根(决策),
+            变异算子::交换主根 => Some(self.交换主根(决策)),
+            变异算子::移动笔画 => Some(self.移动笔画(决策)),
+            变异算子::产生副根 => Some(self.产生副根(决策)),
+            变异算子::湮灭副根 => Some(self.湮灭副根(决策)),
+            变异算子::移动副根 => self.移动副根(决策),
+        };
+        let 变化 = match 变化 {
+            Some(mut 变化) => match self.传播(&mut 变化, 决策) {
+                Ok(()) => 变化,
+                Err(元素) => {
+                    *self.无法传播字根.entry(元素).or_default() += 1;
+                    self.回滚次数[算子.序号()] += 1;
+                    self.改动.回滚(决策);
+                    冰雪清韵决策变化::无变化()
+                }
+            },
+            // 没有可以尝试的移动，不算作回滚，但可能已经改动了一部分元素
+            None => {
+                self.改动.回滚(决策);
+                冰雪清韵决策变化::无变化()
+            }
         };
-        self.传播(&mut 变化, 决策);
+        if let Some(自适应) = &mut self.自适应 {
+            let 无改动 = 变化.是无变化() || !self.改动.有改动(决策);
+            自适应.记录候选(算子, self.改动.元素(), 决策, 无改动);
+        }
         变化
     }
 }
 
 impl 冰雪清韵操作 {
-    pub fn 新建(上下文: &冰雪清韵上下文) -> Self {
+    pub fn 新建(上下文: &冰雪清韵上下文, 种子: u64) -> Self {
         let 棱镜 = 上下文.棱镜.clone();
         let 决策空间 = 上下文.决策空间.clone();
         let 下游字根 = 上下文.下游字根.clone();
@@ -66,51 +142,82 @@ impl 冰雪清韵操作 {
             决策空间,
             下游字根,
             笔画列表,
+            自适应: None,
+            改动: 改动记录::default(),
+            回滚次数: [0; 7],
+            无法传播字根: FxHashMap::default(),
+            rng: StdRng::seed_from_u64(种子),
         };
     }
 
-    fn 改变补码键(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    /// 开启自适应选择，分数记录需要同时交给目标函数，权重变化写入日志路径
+    pub fn 启用自适应(&mut self, 分数记录: 分数记录, 日志路径: PathBuf) {
+        let mut 自适应 = 自适应选择器::新建(分数记录);
+        自适应.设置日志(日志路径);
+        self.自适应 = Some(自适应);
+    }
+
+    /// 各操作因无法传播而回滚的次数、传播失败的字根，以及自适应选择的统计
+    pub fn 总结(&self) -> String {
+        let mut 行: Vec<_> = 变异算子::全部
+            .iter()
+            .zip(&self.回滚次数)
+            .map(|(算子, 次数)| format!("{算子}：回滚 {次数} 次"))
+            .collect();
+        let mut 无法传播: Vec<_> = self.无法传播字根.iter().collect();
+        无法传播.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (元素, 次数) in 无法传播 {
+            let 名称 = &self._棱镜.数字转元素[元素];
+            行.push(format!("无法传播的字根 {名称}：{次数} 次"));
+        }
+        if let Some(自适应) = &self.自适应 {
+            行.push(自适应.总结());
+        }
+        行.join("\n")
+    }
+
+    fn 改变补码键(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         决策.补码键 = 大集合
             .into_iter()
             .filter(|x| !不好的大集合键.contains(x))
-            .choose(&mut rng)
+            .choose(rng)
             .unwrap();
         冰雪清韵决策变化::全局变化()
     }
 
     fn _改变第一主根小码(
-        &self, 决策: &mut 冰雪清韵决策
+        &mut self, 决策: &mut 冰雪清韵决策
     ) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
-        决策.第一主根 = 主根小码.into_iter().choose(&mut rng).unwrap();
+        let rng = &mut self.rng;
+        决策.第一主根 = 主根小码.into_iter().choose(rng).unwrap();
         冰雪清韵决策变化::全局变化()
     }
 
     fn _改变第二主根小码(
-        &self, 决策: &mut 冰雪清韵决策
+        &mut self, 决策: &mut 冰雪清韵决策
     ) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
-        决策.第二主根 = ['o', 'u', ';'].into_iter().choose(&mut rng).unwrap();
+        let rng = &mut self.rng;
+        决策.第二主根 = ['o', 'u', ';'].into_iter().choose(rng).unwrap();
         冰雪清韵决策变化::全局变化()
     }
 
-    fn _移动声母(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn _移动声母(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let (声母, 安排列表) = self
             .决策空间
             .声母
             .iter()
             .map(|&x| (x, &self.决策空间.元素[x]))
             .filter(|(_, y)| y.len() > 1)
-            .choose(&mut rng)
+            .choose(rng)
             .unwrap();
-        决策.元素[声母] = 安排列表.iter().choose(&mut rng).unwrap().安排;
+        self.改动.设置(决策, 声母, 安排列表.iter().choose(rng).unwrap().安排);
         冰雪清韵决策变化::全局变化()
     }
 
-    fn _移动韵母(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn _移动韵母(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let mut 备选列表 = vec![];
         for 韵母 in &self.决策空间.韵母 {
             // if 鼻音韵母列表.contains(韵母) {
@@ -127,18 +234,18 @@ impl 冰雪清韵操作 {
                 备选列表.push((韵母.clone(), 可行安排));
             }
         }
-        let (韵母, 安排列表) = 备选列表.into_iter().choose(&mut rng).unwrap();
-        决策.元素[韵母] = 安排列表.into_iter().choose(&mut rng).unwrap();
+        let (韵母, 安排列表) = 备选列表.into_iter().choose(rng).unwrap();
+        self.改动.设置(决策, 韵母, 安排列表.into_iter().choose(rng).unwrap());
         冰雪清韵决策变化::全局变化()
     }
 
-    fn _交换韵母(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn _交换韵母(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let 非鼻音韵母列表 =
             ["韵-ai", "韵-ei", "韵-ao", "韵-ou", "韵-ü"].map(|s| self._棱镜.元素转数字[s]);
         let 鼻音韵母列表 = ["韵-an", "韵-en", "韵-ang", "韵-eng"].map(|s| self._棱镜.元素转数字[s]);
         let er = self._棱镜.元素转数字["韵-er"];
-        if random::<f64>() < 0.1 {
+        if rng.random::<f64>() < 0.1 {
             let 安排 = 决策.元素[er].clone();
             let mut 可行安排 = vec![];
             for 条件安排 in &self.决策空间.元素[er] {
@@ -146,23 +253,26 @@ impl 冰雪清韵操作 {
                     可行安排.push(条件安排.安排.clone());
                 }
             }
-            决策.元素[er] = 可行安排.into_iter().choose(&mut rng).unwrap();
+            self.改动.设置(决策, er, 可行安排.into_iter().choose(rng).unwrap());
             return 冰雪清韵决策变化::全局变化();
         }
-        let 选择: Vec<_> = if random::<f64>() < 0.5 {
-            鼻音韵母列表.choose_multiple(&mut rng, 2).collect()
+        let 选择: Vec<_> = if rng.random::<f64>() < 0.5 {
+            鼻音韵母列表.choose_multiple(rng, 2).collect()
         } else {
-            非鼻音韵母列表.choose_multiple(&mut rng, 2).collect()
+            非鼻音韵母列表.choose_multiple(rng, 2).collect()
         };
         let (韵母1, 韵母2) = (*选择[0], *选择[1]);
         let 安排1 = 决策.元素[韵母1].clone();
         let 安排2 = 决策.元素[韵母2].clone();
-        决策.元素[韵母1] = 安排2;
-        决策.元素[韵母2] = 安排1;
+        self.改动.设置(决策, 韵母1, 安排2);
+        self.改动.设置(决策, 韵母2, 安排1);
         冰雪清韵决策变化::全局变化()
     }
 
-    fn 传播(&self, 变化: &mut 冰雪清韵决策变化, 决策: &mut 冰雪清韵决策) {
+    /// 沿着条件依赖把变化传播到下游字根，如果某个字根没有合法的安排，返回这个字根
+    fn 传播(
+        &mut self, 变化: &mut 冰雪清韵决策变化, 决策: &mut 冰雪清韵决策
+    ) -> Result<(), 元素> {
         let mut 队列 = VecDeque::new();
         队列.append(&mut 变化.增加字根.clone().into());
         队列.append(&mut 变化.减少字根.clone().into());
@@ -192,13 +302,9 @@ impl 冰雪清韵操作 {
                         元素安排::键位第一 { .. } | 元素安排::键位第二 { .. }
                     )
                 {
-                    let 元素字符串 = &self._棱镜.数字转元素[&元素];
-                    panic!(
-                        "{元素字符串:?} 没有合法的安排，传播失败，全部空间为 {:?}",
-                        self.决策空间.元素[元素]
-                    );
+                    return Err(元素);
                 } else {
-                    let 新安排 = 新安排列表.choose(&mut rng()).unwrap();
+                    let 新安排 = 新安排列表.choose(&mut self.rng).unwrap();
                     if 决策.元素[元素] == 元素安排::未选取 {
                         变化.增加字根.push(元素);
                     } else if 新安排 == &元素安排::未选取 {
@@ -206,7 +312,7 @@ impl 冰雪清韵操作 {
                     } else {
                         变化.移动字根.push(元素);
                     }
-                    决策.元素[元素] = 新安排.clone();
+                    self.改动.设置(决策, 元素, 新安排.clone());
                 }
             }
             for 下游元素 in self.下游字根.get(&元素).unwrap_or(&vec![]) {
@@ -215,10 +321,11 @@ impl 冰雪清韵操作 {
                 }
             }
         }
+        Ok(())
     }
 
-    fn 产生副根(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn 产生副根(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let mut 备选列表 = vec![];
         for 字根 in &self.决策空间.字根 {
             let 安排 = 决策.元素[*字根];
@@ -239,16 +346,17 @@ impl 冰雪清韵操作 {
                 备选列表.push((字根.clone(), 可行安排));
             }
         }
-        if let Some((字根, 可行位置)) = 备选列表.into_iter().choose(&mut rng) {
-            决策.元素[字根] = 可行位置.into_iter().choose(&mut rng).unwrap().clone();
+        if let Some((字根, 可行位置)) = 备选列表.into_iter().choose(rng) {
+            let 安排 = 可行位置.into_iter().choose(rng).unwrap();
+            self.改动.设置(决策, 字根, 安排);
             冰雪清韵决策变化::新建(false, vec![], vec![字根], vec![])
         } else {
             冰雪清韵决策变化::无变化()
         }
     }
 
-    fn 湮灭副根(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn 湮灭副根(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let mut 备选列表 = vec![];
         for 字根 in &self.决策空间.字根 {
             let 安排 = &决策.元素[*字根];
@@ -267,13 +375,13 @@ impl 冰雪清韵操作 {
         if 备选列表.is_empty() {
             return 冰雪清韵决策变化::无变化();
         }
-        let 字根 = *备选列表.iter().choose(&mut rng).unwrap();
-        决策.元素[字根] = 元素安排::未选取;
+        let 字根 = *备选列表.iter().choose(rng).unwrap();
+        self.改动.设置(决策, 字根, 元素安排::未选取);
         冰雪清韵决策变化::新建(false, vec![], vec![], vec![字根])
     }
 
-    fn 移动副根(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn 移动副根(&mut self, 决策: &mut 冰雪清韵决策) -> Option<冰雪清韵决策变化> {
+        let rng = &mut self.rng;
         let mut 备选列表 = vec![];
         for 字根 in &self.决策空间.字根 {
             let 安排 = 决策.元素[*字根];
@@ -303,13 +411,13 @@ impl 冰雪清韵操作 {
                 备选列表.push((字根.clone(), 可行安排));
             }
         }
-        let (字根, 安排列表) = 备选列表.into_iter().choose(&mut rng).unwrap();
-        决策.元素[字根] = 安排列表.into_iter().choose(&mut rng).unwrap().clone();
-        冰雪清韵决策变化::新建(false, vec![字根], vec![], vec![])
+        let (字根, 安排列表) = 备选列表.into_iter().choose(rng)?;
+        self.改动.设置(决策, 字根, 安排列表.into_iter().choose(rng)?);
+        Some(冰雪清韵决策变化::新建(false, vec![字根], vec![], vec![]))
     }
 
-    fn 交换主副根(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn 交换主副根(&mut self, 决策: &mut 冰雪清韵决策) -> Option<冰雪清韵决策变化> {
+        let rng = &mut self.rng;
         let mut 备选列表 = vec![];
         for 字根 in &self.决策空间.字根 {
             let 安排 = 决策.元素[*字根];
@@ -330,28 +438,22 @@ impl 冰雪清韵操作 {
                         元素安排::键位第一 { .. } | 元素安排::键位第二 { .. }
                     )
                 })
-                .choose(&mut rng);
+                .choose(rng);
             if let Some(乱序安排) = 乱序安排 {
                 if 决策.允许(&乱序安排) {
                     备选列表.push((字根.clone(), 乱序安排.安排.clone()));
                 }
             }
         }
-        let (字根, 安排) = 备选列表.into_iter().choose(&mut rng).unwrap();
+        let (字根, 安排) = 备选列表.into_iter().choose(rng)?;
         let 元素安排::键位第二(键位) = 安排 else {
-            panic!("字根 {字根:?} 的安排不是乱序");
+            return None;
         };
-        let 当前该键位上主根 = self
-            .决策空间
-            .字根
-            .iter()
-            .find(|&字根| {
-                !self.笔画列表.contains(字根)
-                    && matches!(决策.元素[*字根], 元素安排::键位第二(k) if k == 键位)
-            })
-            .unwrap()
-            .clone();
-        决策.元素[字根] = 安排;
+        let 当前该键位上主根 = *self.决策空间.字根.iter().find(|&字根| {
+            !self.笔画列表.contains(字根)
+                && matches!(决策.元素[*字根], 元素安排::键位第二(k) if k == 键位)
+        })?;
+        self.改动.设置(决策, 字根, 安排);
         let mut 可行安排 = vec![];
         for 条件安排 in &self.决策空间.元素[当前该键位上主根] {
             if !matches!(条件安排.安排, 元素安排::未选取 | 元素安排::键位第二 { .. })
@@ -360,30 +462,29 @@ impl 冰雪清韵操作 {
                 可行安排.push(条件安排.安排.clone());
             }
         }
-        if 可行安排.is_empty() {
-            panic!(
-                "在 {:?} 中没有 {:?} 可行的安排，无法交换主副根",
-                self.决策空间.元素[当前该键位上主根], self._棱镜.数字转元素[&当前该键位上主根]
-            );
-        }
-        决策.元素[当前该键位上主根] = 可行安排.choose(&mut rng).unwrap().clone();
-        冰雪清韵决策变化::新建(false, vec![字根, 当前该键位上主根], vec![], vec![])
+        self.改动.设置(决策, 当前该键位上主根, 可行安排.choose(rng)?.clone());
+        Some(冰雪清韵决策变化::新建(
+            false,
+            vec![字根, 当前该键位上主根],
+            vec![],
+            vec![],
+        ))
     }
 
-    fn 移动笔画(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
-        let 字根 = *self.笔画列表.choose(&mut rng).unwrap();
+    fn 移动笔画(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
+        let 字根 = *self.笔画列表.choose(rng).unwrap();
         let 安排 = self.决策空间.元素[字根]
             .iter()
-            .choose(&mut rng)
+            .choose(rng)
             .unwrap()
             .clone();
-        决策.元素[字根] = 安排.安排;
+        self.改动.设置(决策, 字根, 安排.安排);
         冰雪清韵决策变化::新建(false, vec![字根], vec![], vec![])
     }
 
-    fn 交换主根(&self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
-        let mut rng = rng();
+    fn 交换主根(&mut self, 决策: &mut 冰雪清韵决策) -> 冰雪清韵决策变化 {
+        let rng = &mut self.rng;
         let 主根列表: Vec<_> = self
             .决策空间
             .字根
@@ -398,7 +499,7 @@ impl 冰雪清韵操作 {
                 None
             })
             .collect();
-        let 采样 = 主根列表.iter().choose_multiple(&mut rng, 2);
+        let 采样 = 主根列表.iter().choose_multiple(rng, 2);
         let (字根1, 键位1) = *采样[0];
         let (字根2, 键位2) = *采样[1];
         if !self.决策空间.元素[字根1]
@@ -410,8 +511,8 @@ impl 冰雪清韵操作 {
         {
             return 冰雪清韵决策变化::无变化();
         }
-        决策.元素[字根1] = 元素安排::键位第二(键位2);
-        决策.元素[字根2] = 元素安排::键位第二(键位1);
+        self.改动.设置(决策, 字根1, 元素安排::键位第二(键位2));
+        self.改动.设置(决策, 字根2, 元素安排::键位第二(键位1));
         冰雪清韵决策变化::新建(false, vec![字根1, 字根2], vec![], vec![])
     }
 }
@@ -0,0 +1,222 @@
+//! 冰雪清韵码表的结构化分析报告。
+//!
+//! 分析结果先整理成 `分析报告`，再写成 `分析.json` 供脚本读取，以及不依赖外部资源的 `分析.html`
+//! 供审阅：表格可以点击表头排序，截断位置可以在页面上调整，点击键盘上的键可以只看以该键开头的编码。
+
+use crate::common::读取选项;
+use chai::错误;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{fs::write, path::PathBuf};
+
+/// 各项分析的截断位置，按通打频序计；可以在 `data/analysis.yaml` 中覆盖
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct 分析截断 {
+    pub 三键字: usize,
+    pub 四键字: usize,
+    pub 差指法: usize,
+    /// 简体、繁体、通打各取前多少字统计全码重码，也是其他几项的候选范围
+    pub 重码: usize,
+}
+
+impl Default for 分析截断 {
+    fn default() -> Self {
+        Self {
+            三键字: 200,
+            四键字: 500,
+            差指法: 1500,
+            重码: 3000,
+        }
+    }
+}
+
+impl 分析截断 {
+    /// 文件不存在时使用默认值，存在但无法解析时返回错误
+    pub fn 读取() -> Result<Self, 错误> {
+        读取选项(None, "data/analysis.yaml")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct 分析条目 {
+    pub 字: char,
+    pub 编码: String,
+    /// 万分之频率
+    pub 频率: f64,
+    /// 通打频序，从 0 开始
+    pub 频序: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct 重码组 {
+    pub 全码: String,
+    pub 字: Vec<char>,
+    /// 除首选外各字的百万分之频率之和
+    pub 次选频率: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct 分析报告 {
+    pub 截断: 分析截断,
+    pub 无理一简多重: Vec<分析条目>,
+    pub 二码字根字: Vec<分析条目>,
+    pub 二简: Vec<分析条目>,
+    pub 三码字根字: Vec<分析条目>,
+    /// 简体前若干字中简码含有同指大跨排或错手的字，页面上再按截断筛选
+    pub 差指法: Vec<分析条目>,
+    pub 三键字: Vec<分析条目>,
+    pub 四键字: Vec<分析条目>,
+    /// 简体、繁体、通打的全码重码
+    pub 全码重码: IndexMap<String, Vec<重码组>>,
+}
+
+const 页面模板: &str = r#"<!DOCTYPE html>
+<html lang="zh">
+<head>
+<meta charset="utf-8">
+<title>冰雪清韵分析</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 2px 8px; }
+th { cursor: pointer; background: #f4f4f4; }
+#键盘 button { width: 2.4em; margin: 1px; font-family: monospace; }
+#键盘 button.选中 { background: #48c; color: white; }
+label { margin-right: 1em; }
+</style>
+</head>
+<body>
+<h1>冰雪清韵分析</h1>
+<div id="截断"></div>
+<div id="键盘"></div>
+<div id="内容"></div>
+<script>
+const 数据 = __数据__;
+const 键盘行 = ["qwertyuiop", "asdfghjkl;", "zxcvbnm,./", "_"];
+let 当前键 = null;
+const 截断 = Object.assign({}, 数据.截断);
+
+function 表格(标题, 列, 行, 排序 = { 列: -1, 升序: true }) {
+  const 节 = document.createElement("section");
+  const 标题元素 = document.createElement("h2");
+  标题元素.textContent = `${标题}（${行.length}）`;
+  节.appendChild(标题元素);
+  const 表 = document.createElement("table");
+  const 表头 = 表.insertRow();
+  列.forEach(([名称, 取值], 序号) => {
+    const 格 = document.createElement("th");
+    格.textContent = 名称 + (排序.列 === 序号 ? (排序.升序 ? " ▲" : " ▼") : "");
+    格.onclick = () => {
+      const 升序 = 排序.列 === 序号 ? !排序.升序 : true;
+      行.sort((a, b) => {
+        const x = 取值(a), y = 取值(b);
+        return (x < y ? -1 : x > y ? 1 : 0) * (升序 ? 1 : -1);
+      });
+      节.replaceWith(表格(标题, 列, 行, { 列: 序号, 升序 }));
+    };
+    表头.appendChild(格);
+  });
+  for (const 项 of 行) {
+    const 行元素 = 表.insertRow();
+    for (const [, 取值] of 列) {
+      const 值 = 取值(项);
+      行元素.insertCell().textContent = typeof 值 === "number" && !Number.isInteger(值) ? 值.toFixed(2) : 值;
+    }
+  }
+  节.appendChild(表);
+  return 节;
+}
+
+const 条目列 = [["字", x => x.字], ["编码", x => x.编码], ["频率（‱）", x => x.频率], ["频序", x => x.频序]];
+const 重码列 = [["全码", x => x.全码], ["字", x => x.字.join(" ")], ["次选频率（μ）", x => x.次选频率]];
+
+function 按键筛选(列表, 编码) {
+  return 当前键 === null ? 列表 : 列表.filter(x => 编码(x).startsWith(当前键));
+}
+
+function 绘制() {
+  const 内容 = document.getElementById("内容");
+  内容.innerHTML = "";
+  const 条目 = (名称, 截断名) => {
+    let 列表 = 数据[名称];
+    if (截断名) 列表 = 列表.filter(x => x.频序 < 截断[截断名]);
+    内容.appendChild(表格(名称, 条目列, 按键筛选(列表, x => x.编码)));
+  };
+  条目("无理一简多重");
+  条目("二码字根字");
+  条目("二简");
+  条目("三码字根字");
+  条目("差指法", "差指法");
+  条目("三键字", "三键字");
+  条目("四键字", "四键字");
+  for (const [名称, 列表] of Object.entries(数据.全码重码)) {
+    内容.appendChild(表格(`${名称}全码重码`, 重码列, 按键筛选(列表, x => x.全码)));
+  }
+}
+
+function 初始化() {
+  const 截断区 = document.getElementById("截断");
+  for (const 名称 of ["差指法", "三键字", "四键字"]) {
+    const 标签 = document.createElement("label");
+    标签.textContent = `${名称}截断 `;
+    const 输入 = document.createElement("input");
+    输入.type = "number";
+    输入.min = 0;
+    输入.max = 数据.截断.重码;
+    输入.value = 截断[名称];
+    输入.onchange = () => { 截断[名称] = Number(输入.value); 绘制(); };
+    标签.appendChild(输入);
+    截断区.appendChild(标签);
+  }
+  const 键盘 = document.getElementById("键盘");
+  for (const 行 of 键盘行) {
+    const 行元素 = document.createElement("div");
+    for (const 键 of 行) {
+      const 按钮 = document.createElement("button");
+      按钮.textContent = 键;
+      按钮.onclick = () => {
+        当前键 = 当前键 === 键 ? null : 键;
+        键盘.querySelectorAll("button").forEach(x => x.classList.toggle("选中", x.textContent === 当前键));
+        绘制();
+      };
+      行元素.appendChild(按钮);
+    }
+    键盘.appendChild(行元素);
+  }
+  绘制();
+}
+
+初始化();
+</script>
+</body>
+</html>
+"#;
+
+impl 分析报告 {
+    /// 差指法、三键字、四键字只保留截断以内的条目
+    pub fn 按截断筛选(&self) -> Self {
+        let 筛选 = |列表: &[分析条目], 截断: usize| -> Vec<_> {
+            列表.iter().filter(|x| x.频序 < 截断).cloned().collect()
+        };
+        Self {
+            差指法: 筛选(&self.差指法, self.截断.差指法),
+            三键字: 筛选(&self.三键字, self.截断.三键字),
+            四键字: 筛选(&self.四键字, self.截断.四键字),
+            ..self.clone()
+        }
+    }
+
+    /// `分析.json` 按截断筛选；`分析.html` 保留候选范围内的全部条目，在页面上调整截断
+    pub fn 写入(&self, 目录: &PathBuf) -> std::io::Result<()> {
+        let 数据 = serde_json::to_string(self)?;
+        let 筛选后 = self.按截断筛选();
+        write(
+            目录.join("分析.json"),
+            serde_json::to_string_pretty(&筛选后)?,
+        )?;
+        // 避免数据中的 </script> 提前结束脚本
+        let 页面 = 页面模板.replace("__数据__", &数据.replace("</", "<\\/"));
+        write(目录.join("分析.html"), 页面)
+    }
+}
@@ -1,11 +1,12 @@
-use crate::common::转换;
+use crate::common::{关联序号, 展开归并, 构建关联映射, 转换};
 use crate::snow2::{
-    冰雪二拼上下文, 冰雪二拼信息, 冰雪二拼决策, 线性化决策, 编码
+    冰雪二拼上下文, 冰雪二拼信息, 冰雪二拼决策, 冰雪二拼决策变化, 冰雪二拼字根安排, 冰雪二拼词信息,
+    线性化决策, 编码,
 };
 use chai::encoders::编码器;
 use chai::{元素, 编码信息};
 use chai::{棱镜, 错误};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::iter::zip;
 
 #[derive(Default, Clone, Debug)]
@@ -15,6 +16,9 @@ pub struct 冰雪二拼编码信息 {
     pub 频率: u64,
     pub 指数频率: f64,
     pub 选重: bool,
+    /// 全码在重码中的位置，0 表示首选
+    pub 候选位置: u8,
+    pub 受独立规则影响: bool,
 }
 
 pub struct 冰雪二拼编码器 {
@@ -25,6 +29,28 @@ pub struct 冰雪二拼编码器 {
     pub 棱镜: 棱镜,
     pub 韵母声调映射: FxHashMap<元素, (String, usize)>,
     pub 编码结果: Vec<冰雪二拼编码信息>,
+    pub 多字词列表: Vec<冰雪二拼词信息>,
+    /// 多字词只有全码，简码与全码相同
+    pub 多字编码结果: Vec<冰雪二拼编码信息>,
+    pub 独立规则: bool,
+    /// 元素到序列中含有它的字的序号
+    pub 字关联映射: FxHashMap<元素, Vec<usize>>,
+    /// 元素到某个字的序列中含有它的词的序号
+    pub 词关联映射: FxHashMap<元素, Vec<usize>>,
+    /// 是否已经完整编码过一次，之后才能按变化增量更新
+    已编码: bool,
+    /// 二简和三简占用的编码，与全码分开计数
+    简码空间: Vec<u8>,
+    /// 每个全码上按序号排列的字词，多字词的序号接在单字之后
+    全码桶: FxHashMap<usize, Vec<usize>>,
+    /// 每个二简和三简编码上按序号排列的以它为前缀的字
+    前缀桶: FxHashMap<usize, Vec<usize>>,
+    /// 每个字占用的简码长度，0 表示没有简码
+    简码长度: Vec<u8>,
+    /// 上一次编码中全码、简码或重码位置有变化的字词，多字词的序号接在单字之后
+    pub 变化序号: Vec<usize>,
+    pub 独立规则影响字数: usize,
+    pub 独立规则影响频数: u64,
 }
 
 impl 冰雪二拼编码器 {
@@ -32,12 +58,18 @@ impl 冰雪二拼编码器 {
         let 词列表 = 上下文.信息列表.clone();
         let 编码空间大小 = 编码::编码空间大小();
         let 全码空间 = vec![Default::default(); 编码空间大小];
-        let 简码空间 = 全码空间.clone();
         let 编码器 = Self {
             进制: 上下文.棱镜.进制,
             词列表,
-            全码空间,
-            一简空间: 简码空间,
+            全码空间: 全码空间.clone(),
+            一简空间: 全码空间.clone(),
+            简码空间: 全码空间,
+            全码桶: FxHashMap::default(),
+            前缀桶: FxHashMap::default(),
+            简码长度: vec![0; 上下文.信息列表.len()],
+            变化序号: vec![],
+            独立规则影响字数: 0,
+            独立规则影响频数: 0,
             棱镜: 上下文.棱镜.clone(),
             韵母声调映射: 上下文.韵母声调映射.clone(),
             编码结果: 上下文
@@ -50,45 +82,65 @@ impl 冰雪二拼编码器 {
                     频率: 词.频率,
                     指数频率: ((索引.min(6000) as f64) / -2000.0).exp(),
                     选重: false,
+                    候选位置: 0,
+                    受独立规则影响: false,
+                })
+                .collect(),
+            独立规则: 上下文.独立规则,
+            字关联映射: 构建关联映射(上下文.信息列表.iter().map(|x| x.序列)),
+            词关联映射: 构建关联映射(
+                上下文
+                    .词信息列表
+                    .iter()
+                    .map(|x| x.字.iter().flat_map(|y| y.序列)),
+            ),
+            已编码: false,
+            多字词列表: 上下文.词信息列表.clone(),
+            多字编码结果: 上下文
+                .词信息列表
+                .iter()
+                .enumerate()
+                .map(|(索引, 词)| 冰雪二拼编码信息 {
+                    频率: 词.频率,
+                    指数频率: ((索引.min(6000) as f64) / -2000.0).exp(),
+                    ..Default::default()
                 })
                 .collect(),
         };
         Ok(编码器)
     }
 
-    pub fn 重置空间(&mut self) {
-        self.全码空间.iter_mut().for_each(|x| {
-            *x = 0;
-        });
-        self.一简空间.iter_mut().for_each(|x| {
-            *x = 0;
+    /// 变化涉及的所有元素：带调韵母按韵母和声调展开，字根连同归并到它的字根
+    fn 展开变化(
+        &self, 决策: &冰雪二拼决策, 变化: &冰雪二拼决策变化
+    ) -> FxHashSet<元素> {
+        let mut 元素集合: FxHashSet<_> = 变化.声母.iter().cloned().collect();
+        for (元素, (韵母, 声调)) in &self.韵母声调映射 {
+            if 变化.韵母.contains(韵母) || 变化.声调.contains(声调) {
+                元素集合.insert(*元素);
+            }
+        }
+        let mut 字根集合: FxHashSet<_> = 变化.字根.iter().cloned().collect();
+        let 归并关系 = 决策.字根.iter().filter_map(|(元素, 安排)| match 安排 {
+            冰雪二拼字根安排::归并(x) => Some((*元素, *x)),
+            _ => None,
         });
+        展开归并(&mut 字根集合, 归并关系);
+        元素集合.extend(字根集合);
+        元素集合
     }
 
+    /// 不区分独立时，副根总是取两码
     #[inline(always)]
-    fn 全码规则(词: &冰雪二拼信息, 映射: &线性化决策) -> 编码 {
+    fn 不区分独立(词: &冰雪二拼信息, 映射: &线性化决策) -> 编码 {
         let 序列 = &词.序列;
         let mut 全码 = [映射[序列[0]].0, 映射[序列[1]].0, 0, 0, 0];
         let (字根一, 字根二) = (序列[2], 序列[3]);
         if 字根二 == 0 {
-            // if 词.独立一 {
-                (全码[2], 全码[3]) = 映射[字根一];
-            // } else {
-            //     全码[2] = 映射[字根一].0;
-            // }
+            (全码[2], 全码[3]) = 映射[字根一];
         } else {
             let 编码一 = 映射[字根一];
             let 编码二 = 映射[字根二];
-            // if 词.独立一 && 编码一.1 != 0 {
-            //     (全码[2], 全码[3]) = 编码一;
-            //     全码[4] = 编码二.0;
-            // } else if 词.独立二 && 编码二.1 != 0 {
-            //     全码[2] = 编码一.0;
-            //     (全码[3], 全码[4]) = 编码二;
-            // } else {
-            //     全码[2] = 编码一.0;
-            //     全码[3] = 编码二.0;
-            // }
             if 编码一.1 != 0 {
                 (全码[2], 全码[3]) = 编码一;
                 全码[4] = 编码二.0;
@@ -103,40 +155,223 @@ impl 冰雪二拼编码器 {
         全码
     }
 
-    fn 输出全码(&mut self, 映射: &线性化决策) {
-        for (词, 编码信息) in zip(&self.词列表, self.编码结果.iter_mut()) {
-            编码信息.全码 = 冰雪二拼编码器::全码规则(词, 映射);
+    /// 区分独立时，只有独立的字根上的副根取两码
+    #[inline(always)]
+    fn 区分独立(词: &冰雪二拼信息, 映射: &线性化决策) -> 编码 {
+        let 序列 = &词.序列;
+        let mut 全码 = [映射[序列[0]].0, 映射[序列[1]].0, 0, 0, 0];
+        let (字根一, 字根二) = (序列[2], 序列[3]);
+        if 字根二 == 0 {
+            if 词.独立一 {
+                (全码[2], 全码[3]) = 映射[字根一];
+            } else {
+                全码[2] = 映射[字根一].0;
+            }
+        } else {
+            let 编码一 = 映射[字根一];
+            let 编码二 = 映射[字根二];
+            if 词.独立一 && 编码一.1 != 0 {
+                (全码[2], 全码[3]) = 编码一;
+                全码[4] = 编码二.0;
+            } else if 词.独立二 && 编码二.1 != 0 {
+                全码[2] = 编码一.0;
+                (全码[3], 全码[4]) = 编码二;
+            } else {
+                全码[2] = 编码一.0;
+                全码[3] = 编码二.0;
+            }
+        }
+        全码
+    }
+
+    /// 返回全码，以及独立规则是否改变了这个全码
+    #[inline(always)]
+    fn 全码规则(
+        词: &冰雪二拼信息, 映射: &线性化决策, 独立规则: bool
+    ) -> (编码, bool) {
+        let 全码 = 冰雪二拼编码器::不区分独立(词, 映射);
+        if !独立规则 {
+            return (全码, false);
+        }
+        let 独立全码 = 冰雪二拼编码器::区分独立(词, 映射);
+        (独立全码, 独立全码 != 全码)
+    }
+
+    /// 返回原来的全码
+    fn 生成字全码(&mut self, 序号: usize, 映射: &线性化决策) -> 编码 {
+        let 编码信息 = &mut self.编码结果[序号];
+        let 原全码 = 编码信息.全码;
+        if 编码信息.受独立规则影响 {
+            self.独立规则影响字数 -= 1;
+            self.独立规则影响频数 -= 编码信息.频率;
+        }
+        (编码信息.全码, 编码信息.受独立规则影响) =
+            冰雪二拼编码器::全码规则(&self.词列表[序号], 映射, self.独立规则);
+        if 编码信息.受独立规则影响 {
+            self.独立规则影响字数 += 1;
+            self.独立规则影响频数 += 编码信息.频率;
+        }
+        原全码
+    }
+
+    /// 返回原来的全码
+    fn 生成词全码(&mut self, 序号: usize, 映射: &线性化决策) -> 编码 {
+        let 词 = &self.多字词列表[序号];
+        let 各字全码: Vec<_> = 词
+            .字
+            .iter()
+            .map(|x| 冰雪二拼编码器::全码规则(x, 映射, self.独立规则).0)
+            .collect();
+        let mut 全码 = 编码::default();
+        for (位置, (字序号, 码序号)) in 词.规则.iter().enumerate() {
+            全码[位置] = 各字全码[*字序号][*码序号];
+        }
+        let 编码信息 = &mut self.多字编码结果[序号];
+        let 原全码 = 编码信息.全码;
+        编码信息.全码 = 全码;
+        编码信息.简码 = 全码;
+        原全码
+    }
+
+    /// 按序号取字或词的编码信息，多字词的序号接在单字之后
+    fn 编码信息(&mut self, 序号: usize) -> &mut 冰雪二拼编码信息 {
+        let 字数 = self.编码结果.len();
+        if 序号 < 字数 {
+            &mut self.编码结果[序号]
+        } else {
+            &mut self.多字编码结果[序号 - 字数]
+        }
+    }
+
+    fn 前缀(全码: &编码) -> [usize; 2] {
+        [
+            [全码[0], 全码[1], 0, 0, 0].hash(),
+            [全码[0], 全码[1], 全码[2], 0, 0].hash(),
+        ]
+    }
+
+    fn 插入(桶: &mut FxHashMap<usize, Vec<usize>>, 编码: usize, 序号: usize) {
+        let 列表 = 桶.entry(编码).or_default();
+        if let Err(位置) = 列表.binary_search(&序号) {
+            列表.insert(位置, 序号);
+        }
+    }
+
+    fn 移除(桶: &mut FxHashMap<usize, Vec<usize>>, 编码: usize, 序号: usize) {
+        if let Some(列表) = 桶.get_mut(&编码) {
+            if let Ok(位置) = 列表.binary_search(&序号) {
+                列表.remove(位置);
+            }
+        }
+    }
+
+    /// 按频率顺序把全码放入全码空间，多字词在单字之后，与单字重码时单字优先
+    fn 统计重码(&mut self) {
+        self.全码空间.fill(0);
+        self.全码桶.clear();
+        self.前缀桶.clear();
+        let 字数 = self.编码结果.len();
+        let 全部编码结果 = self.编码结果.iter_mut().chain(self.多字编码结果.iter_mut());
+        for (序号, 编码信息) in 全部编码结果.enumerate() {
             let hash = 编码信息.全码.hash();
-            编码信息.选重 = self.全码空间[hash] > 0;
+            编码信息.候选位置 = self.全码空间[hash];
+            编码信息.选重 = 编码信息.候选位置 > 0;
             self.全码空间[hash] += 1;
+            self.全码桶.entry(hash).or_default().push(序号);
+            if 序号 < 字数 {
+                for 前缀 in Self::前缀(&编码信息.全码) {
+                    Self::插入(&mut self.前缀桶, 前缀, 序号);
+                }
+            }
         }
     }
 
-    fn 输出简码(&mut self) {
-        for 编码信息 in self.编码结果.iter_mut() {
+    /// 只移动全码改变了的字词，并重排它们进出的全码上的候选位置
+    ///
+    /// 返回需要重新分配简码的第一个字：简码按频率顺序贪心分配，这个字之前的简码都不受影响
+    fn 更新重码(&mut self, 全码变化: &[(usize, 编码)]) -> usize {
+        let 字数 = self.编码结果.len();
+        let mut 起点 = 字数;
+        let mut 涉及编码 = FxHashSet::default();
+        let mut 空闲变化 = vec![];
+        for &(序号, 原全码) in 全码变化 {
+            let 新全码 = self.编码信息(序号).全码;
+            let (原, 新) = (原全码.hash(), 新全码.hash());
+            Self::移除(&mut self.全码桶, 原, 序号);
+            Self::插入(&mut self.全码桶, 新, 序号);
+            self.全码空间[原] -= 1;
+            self.全码空间[新] += 1;
+            if self.全码空间[原] == 0 {
+                空闲变化.push(原);
+            }
+            if self.全码空间[新] == 1 {
+                空闲变化.push(新);
+            }
+            涉及编码.extend([原, 新]);
+            if 序号 < 字数 {
+                起点 = 起点.min(序号);
+                for (原前缀, 新前缀) in zip(Self::前缀(&原全码), Self::前缀(&新全码))
+                {
+                    Self::移除(&mut self.前缀桶, 原前缀, 序号);
+                    Self::插入(&mut self.前缀桶, 新前缀, 序号);
+                }
+            }
+            self.变化序号.push(序号);
+        }
+        for hash in 涉及编码 {
+            let 列表 = self.全码桶[&hash].clone();
+            for (位置, 序号) in 列表.into_iter().enumerate() {
+                let 编码信息 = self.编码信息(序号);
+                if 编码信息.候选位置 != 位置 as u8 {
+                    编码信息.候选位置 = 位置 as u8;
+                    编码信息.选重 = 位置 > 0;
+                    self.变化序号.push(序号);
+                }
+            }
+        }
+        // 全码空间上某个编码是否空闲的变化，会影响以它为二简或三简的字
+        for hash in 空闲变化 {
+            if let Some(&序号) = self.前缀桶.get(&hash).and_then(|x| x.first()) {
+                起点 = 起点.min(序号);
+            }
+        }
+        起点
+    }
+
+    /// 从第 `起点` 个字开始重新分配简码
+    fn 输出简码(&mut self, 起点: usize) {
+        for (编码信息, 简码长度) in zip(&self.编码结果, &self.简码长度).skip(起点)
+        {
+            match 简码长度 {
+                1 => self.一简空间[编码信息.简码[0] as usize] -= 1,
+                2 | 3 => self.简码空间[编码信息.简码.hash()] -= 1,
+                _ => {}
+            }
+        }
+        for 序号 in 起点..self.编码结果.len() {
+            let 编码信息 = &mut self.编码结果[序号];
             let 全码 = 编码信息.全码;
+            let 原简码 = 编码信息.简码;
             let 一简 = [全码[0], 0, 0, 0, 0];
-            let 重数 = self.一简空间[一简[0] as usize];
-            if 重数 == 0 {
-                self.一简空间[一简[0] as usize] += 1;
-                编码信息.简码 = 一简;
-                continue;
-            }
             let 二简 = [全码[0], 全码[1], 0, 0, 0];
-            let 重数 = self.全码空间[二简.hash()];
-            if 重数 == 0 {
-                self.全码空间[二简.hash()] += 1;
-                编码信息.简码 = 二简;
-                continue;
-            }
             let 三简 = [全码[0], 全码[1], 全码[2], 0, 0];
-            let 重数 = self.全码空间[三简.hash()];
-            if 重数 == 0 {
-                self.全码空间[三简.hash()] += 1;
-                编码信息.简码 = 三简;
-                continue;
+            let [二简空闲, 三简空闲] =
+                [二简, 三简].map(|x| self.全码空间[x.hash()] == 0 && self.简码空间[x.hash()] == 0);
+            (编码信息.简码, self.简码长度[序号]) = if self.一简空间[一简[0] as usize] == 0 {
+                self.一简空间[一简[0] as usize] += 1;
+                (一简, 1)
+            } else if 二简空闲 {
+                self.简码空间[二简.hash()] += 1;
+                (二简, 2)
+            } else if 三简空闲 {
+                self.简码空间[三简.hash()] += 1;
+                (三简, 3)
+            } else {
+                (全码, 0)
+            };
+            if 编码信息.简码 != 原简码 {
+                self.变化序号.push(序号);
             }
-            编码信息.简码 = 全码;
         }
     }
 }
@@ -145,11 +380,51 @@ impl 编码器 for 冰雪二拼编码器 {
     type 决策 = 冰雪二拼决策;
 
     fn 编码(
-        &mut self, 决策: &Self::决策, _变化: &Option<()>, _输出: &mut [编码信息]
+        &mut self,
+        决策: &Self::决策,
+        变化: &Option<冰雪二拼决策变化>,
+        _输出: &mut [编码信息],
     ) {
-        self.重置空间();
         let 映射 = 决策.线性化(&self.棱镜, &self.韵母声调映射);
-        self.输出全码(&映射);
-        self.输出简码();
+        let 字数 = self.编码结果.len();
+        self.变化序号.clear();
+        match 变化 {
+            // 只重新生成受影响的字和词的全码，再从全码改变的字词出发更新重码和简码
+            Some(变化) if self.已编码 => {
+                let 元素集合 = self.展开变化(决策, 变化);
+                let mut 全码变化 = vec![];
+                for 序号 in 关联序号(&self.字关联映射, &元素集合) {
+                    let 原全码 = self.生成字全码(序号, &映射);
+                    if 原全码 != self.编码结果[序号].全码 {
+                        全码变化.push((序号, 原全码));
+                    }
+                }
+                for 序号 in 关联序号(&self.词关联映射, &元素集合) {
+                    let 原全码 = self.生成词全码(序号, &映射);
+                    if 原全码 != self.多字编码结果[序号].全码 {
+                        全码变化.push((字数 + 序号, 原全码));
+                    }
+                }
+                let 起点 = self.更新重码(&全码变化);
+                self.输出简码(起点);
+                self.变化序号.sort_unstable();
+                self.变化序号.dedup();
+            }
+            _ => {
+                for 序号 in 0..字数 {
+                    self.生成字全码(序号, &映射);
+                }
+                for 序号 in 0..self.多字编码结果.len() {
+                    self.生成词全码(序号, &映射);
+                }
+                self.统计重码();
+                self.一简空间.fill(0);
+                self.简码空间.fill(0);
+                self.简码长度.fill(0);
+                self.输出简码(0);
+                self.变化序号 = (0..字数 + self.多字编码结果.len()).collect();
+                self.已编码 = true;
+            }
+        }
     }
 }
@@ -1,14 +1,34 @@
+pub mod cli;
 pub mod encoder;
 pub mod objective;
 pub mod operators;
-use crate::common::转换;
+use crate::{
+    chart::{标注类别, 键盘图},
+    common::{get_pua_mapper, 读取选项, 转换},
+    exporters::{导出, 导出条目, 导出码表, 条目类型},
+    qingyun::context::写入文本文件,
+    snow2::encoder::冰雪二拼编码器,
+};
 use chai::{
-    config::{Mapped, 配置}, contexts::{上下文, 合并初始决策, 拓扑排序}, interfaces::默认输入, objectives::metric::键盘布局, optimizers::决策, 元素, 棱镜
+    config::{Mapped, 配置},
+    contexts::{上下文, 合并初始决策, 拓扑排序},
+    interfaces::默认输入,
+    objectives::metric::键盘布局,
+    optimizers::决策,
+    元素, 原始当量信息, 原始键位分布信息, 棱镜, 码表项, 错误,
 };
 use chrono::Local;
 use indexmap::IndexMap;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
 use serde_yaml::to_string;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Error, ErrorKind, Write},
+    iter::zip,
+    path::PathBuf,
+};
 
 pub const 声调总数: usize = 5;
 pub const 大: usize = 21;
@@ -83,11 +103,47 @@ pub struct 冰雪二拼决策 {
 
 pub type 线性化决策 = Vec<(键, 键)>;
 
+/// 一次变异中改变了安排的声母、韵母、声调和字根
+#[derive(Clone, Debug, Default)]
+pub struct 冰雪二拼决策变化 {
+    pub 声母: Vec<元素>,
+    /// 不带调的韵母
+    pub 韵母: Vec<String>,
+    pub 声调: Vec<usize>,
+    pub 字根: Vec<元素>,
+}
+
+impl 冰雪二拼决策变化 {
+    pub fn 无变化() -> Self {
+        Self::default()
+    }
+
+    pub fn 是无变化(&self) -> bool {
+        self.声母.is_empty() && self.韵母.is_empty() && self.声调.is_empty() && self.字根.is_empty()
+    }
+}
+
+fn 并集<T: Clone + PartialEq>(甲: &[T], 乙: &[T]) -> Vec<T> {
+    let mut 结果 = 甲.to_vec();
+    for x in 乙 {
+        if !结果.contains(x) {
+            结果.push(x.clone());
+        }
+    }
+    结果
+}
+
 impl 决策 for 冰雪二拼决策 {
-    type 变化 = ();
+    type 变化 = 冰雪二拼决策变化;
 
-    fn 除法(_旧变化: &Self::变化, _新变化: &Self::变化) -> Self::变化 {
-        ()
+    // 被拒绝的变异和新的变异涉及的元素都需要重新编码
+    fn 除法(旧变化: &Self::变化, 新变化: &Self::变化) -> Self::变化 {
+        冰雪二拼决策变化 {
+            声母: 并集(&旧变化.声母, &新变化.声母),
+            韵母: 并集(&旧变化.韵母, &新变化.韵母),
+            声调: 并集(&旧变化.声调, &新变化.声调),
+            字根: 并集(&旧变化.字根, &新变化.字根),
+        }
     }
 }
 
@@ -135,7 +191,12 @@ pub struct 冰雪二拼上下文 {
     pub 韵母声调映射: FxHashMap<元素, (String, usize)>,
     pub 字根列表: Vec<元素>,
     pub 信息列表: Vec<冰雪二拼信息>,
+    pub 词信息列表: Vec<冰雪二拼词信息>,
+    pub 独立规则: bool,
+    pub 声母约束: 冰雪二拼声母约束,
     pub 棱镜: 棱镜,
+    pub 原始键位分布信息: 原始键位分布信息,
+    pub 原始当量信息: 原始当量信息,
 }
 
 impl 上下文 for 冰雪二拼上下文 {
@@ -191,13 +252,230 @@ pub struct 冰雪二拼信息 {
     pub 独立二: bool,
 }
 
+/// 每个字的序列以独立标记结尾，表示两个字根是否独立
+const 独立标记: [&str; 4] = ["q", "w", "e", "r"];
+
+impl 冰雪二拼信息 {
+    fn 解析(词: char, 频率: u64, 原始序列: &[&str], 棱镜: &棱镜) -> Self {
+        let mut 序列 = [0; 4];
+        for (i, 元素名称) in 原始序列
+            .iter()
+            .filter(|x| !独立标记.contains(*x))
+            .enumerate()
+        {
+            let 元素编号 = 棱镜.元素转数字.get(*元素名称).unwrap();
+            序列[i] = *元素编号;
+        }
+        let (独立一, 独立二) = match 原始序列[原始序列.len() - 1] {
+            "q" => (true, true),
+            "w" => (false, true),
+            "e" => (true, false),
+            "r" => (false, false),
+            _ => unreachable!(),
+        };
+        Self {
+            词,
+            频率,
+            序列,
+            独立一,
+            独立二,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct 冰雪二拼词信息 {
+    pub 词: String,
+    pub 频率: u64,
+    /// 各字的信息，频率沿用词频
+    pub 字: Vec<冰雪二拼信息>,
+    /// 词的全码依次取哪个字的第几码
+    pub 规则: Vec<(usize, usize)>,
+}
+
+/// 冰雪二拼的编码规则选项，可以在 `data/snow2.yaml` 中覆盖
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct 冰雪二拼选项 {
+    pub 构词规则: 冰雪二拼构词规则,
+    /// 是否区分字根是否独立：开启时只有独立的副根取两码，不独立的副根只取第一码
+    pub 独立规则: bool,
+    pub 声母布局: 冰雪二拼声母布局选项,
+}
+
+/// 声母布局的搜索选项，声母不带「冰声」前缀，键用字母表示
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪二拼声母布局选项 {
+    /// 是否在优化时移动声母
+    pub 启用: bool,
+    /// 始终留在初始键位上的声母
+    pub 固定声母: Vec<String>,
+    /// 不能放置声母的键
+    pub 禁用键: String,
+    /// 必须放置声母的键，不能整键移出
+    pub 必用键: String,
+    /// 整键移出时，关于中排的反射键是这些键也可以移出
+    pub 反射例外键: String,
+}
+
+impl Default for 冰雪二拼声母布局选项 {
+    fn default() -> Self {
+        Self {
+            启用: false,
+            固定声母: vec![],
+            禁用键: String::new(),
+            必用键: "sdfghjkl".to_string(),
+            反射例外键: ",.".to_string(),
+        }
+    }
+}
+
+/// 解析为元素和键之后的声母布局约束
+#[derive(Debug, Clone, Default)]
+pub struct 冰雪二拼声母约束 {
+    pub 启用: bool,
+    pub 固定声母: FxHashSet<元素>,
+    /// 可以放置声母的键，由于编码空间的限制只能是大集合中的键
+    pub 可用键: Vec<键>,
+    pub 必用键: Vec<键>,
+    pub 反射例外键: Vec<键>,
+}
+
+impl 冰雪二拼声母布局选项 {
+    /// 把名称和字母转换为元素和键，并检查初始决策是否满足约束，有问题时返回错误并列出所有问题
+    pub fn 解析(
+        &self,
+        棱镜: &棱镜,
+        初始决策: &冰雪二拼决策,
+    ) -> Result<冰雪二拼声母约束, 错误> {
+        let mut 问题 = vec![];
+        let mut 转键 = |字母列表: &str| -> Vec<键> {
+            字母列表
+                .chars()
+                .filter_map(|x| match 棱镜.键转数字.get(&x) {
+                    Some(键) => Some(*键 as 键),
+                    None => {
+                        问题.push(format!("声母布局中的键 {x} 不存在"));
+                        None
+                    }
+                })
+                .collect()
+        };
+        let 禁用键 = 转键(&self.禁用键);
+        let 可用键: Vec<_> = 转键(&大集合.iter().collect::<String>())
+            .into_iter()
+            .filter(|x| !禁用键.contains(x))
+            .collect();
+        let 必用键 = 转键(&self.必用键);
+        let 反射例外键 = 转键(&self.反射例外键);
+        let mut 固定声母 = FxHashSet::default();
+        for x in &self.固定声母 {
+            match 棱镜.元素转数字.get(&format!("冰声{x}")) {
+                Some(元素) => {
+                    固定声母.insert(*元素);
+                }
+                None => 问题.push(format!("固定声母 {x} 不存在")),
+            }
+        }
+        let 约束 = 冰雪二拼声母约束 {
+            启用: self.启用,
+            固定声母,
+            可用键,
+            必用键,
+            反射例外键,
+        };
+        let 声母键: FxHashSet<_> = 初始决策.声母.values().cloned().collect();
+        for (元素, 键) in &初始决策.声母 {
+            if !约束.可用键.contains(键) {
+                问题.push(format!(
+                    "声母 {} 所在的键 {} 不能放置声母",
+                    棱镜.数字转元素[元素],
+                    棱镜.数字转键[&(*键 as u64)]
+                ));
+            }
+        }
+        for 键 in &约束.必用键 {
+            if !声母键.contains(键) {
+                问题.push(format!(
+                    "必用键 {} 上没有声母",
+                    棱镜.数字转键[&(*键 as u64)]
+                ));
+            }
+        }
+        if !问题.is_empty() {
+            let 消息 = format!("声母布局选项有误：\n{}", 问题.join("\n"));
+            return Err(Error::new(ErrorKind::InvalidData, 消息).into());
+        }
+        Ok(约束)
+    }
+}
+
+impl 冰雪二拼选项 {
+    /// 文件不存在时使用默认值，存在但无法解析时返回错误
+    pub fn 读取() -> Result<Self, 错误> {
+        读取选项(None, "data/snow2.yaml")
+    }
+}
+
+/// 多字词的全码规则，键为词长，值为构词公式；词长超过所有键时用最长的公式。
+///
+/// 公式由「字母 + 码位」组成：大写字母 A、B、C…… 表示第几个字，Z、Y 表示倒数第一、第二个字；
+/// 小写字母 a 到 e 表示该字全码中的第几码，即声母、带调韵母和三个形码。
+/// 由于编码空间的限制，第一码只能取声母，第三码以后只能取形码。
+#[derive(Debug, Clone, Deserialize)]
+pub struct 冰雪二拼构词规则(BTreeMap<usize, String>);
+
+impl Default for 冰雪二拼构词规则 {
+    fn default() -> Self {
+        Self(BTreeMap::from([
+            (2, "AaBaAcBc".to_string()),
+            (3, "AaBaAcBcCc".to_string()),
+            (4, "AaBaAcBcZc".to_string()),
+        ]))
+    }
+}
+
+impl 冰雪二拼构词规则 {
+    /// 把公式展开为 `(字序号, 码序号)` 的列表，公式不合法时返回错误
+    pub fn 查找(&self, 词长: usize) -> Result<Vec<(usize, usize)>, 错误> {
+        let 无效 = |消息: String| -> 错误 { Error::new(ErrorKind::InvalidData, 消息).into() };
+        let Some((_, 公式)) = self.0.range(..=词长).next_back() else {
+            return Err(无效(format!("没有适用于词长 {词长} 的构词规则")));
+        };
+        let 字符列表: Vec<char> = 公式.chars().collect();
+        if 字符列表.len() % 2 != 0 || !(4..=10).contains(&字符列表.len()) {
+            return Err(无效(format!("构词规则 {公式} 应当包含二到五码")));
+        }
+        let mut 规则 = vec![];
+        for (位置, 码元) in 字符列表.chunks(2).enumerate() {
+            let 字序号 = match 码元[0] {
+                'Z' => 词长 - 1,
+                'Y' => 词长 - 2,
+                x @ 'A'..='X' => x as usize - 'A' as usize,
+                x => return Err(无效(format!("构词规则 {公式} 中的 {x} 不是字序号"))),
+            };
+            let 码序号 = match 码元[1] {
+                x @ 'a'..='e' => x as usize - 'a' as usize,
+                x => return Err(无效(format!("构词规则 {公式} 中的 {x} 不是码序号"))),
+            };
+            if 字序号 >= 词长 {
+                return Err(无效(format!("构词规则 {公式} 超出了词长 {词长}")));
+            }
+            if (位置 == 0 && 码序号 != 0) || (位置 >= 2 && 码序号 < 2) {
+                return Err(无效(format!(
+                    "构词规则 {公式} 的第一码必须是声母，第三码以后必须是形码"
+                )));
+            }
+            规则.push((字序号, 码序号));
+        }
+        Ok(规则)
+    }
+}
+
 impl 冰雪二拼上下文 {
-    pub fn 新建(输入: &默认输入) -> Self {
-        let 布局 = 输入.配置.form.clone();
-        let 原始决策 = 布局.mapping;
-        let mut 原始决策空间 = 布局.mapping_space.unwrap_or_default();
-        合并初始决策(&mut 原始决策空间, &原始决策);
-        let (所有元素, _) = 拓扑排序(&原始决策空间).unwrap();
+    /// 键按小集合、大集合、逗号、句号编号，元素接在键之后
+    pub fn 生成棱镜(所有元素: &[String]) -> 棱镜 {
         let mut 元素转数字 = FxHashMap::default();
         let mut 数字转元素 = FxHashMap::default();
         let mut 键转数字 = FxHashMap::default();
@@ -219,6 +497,23 @@ impl 冰雪二拼上下文 {
             元素转数字.insert(元素名称.clone(), 序号);
             数字转元素.insert(序号, 元素名称.clone());
         }
+        棱镜 {
+            进制,
+            元素转数字,
+            数字转元素,
+            键转数字,
+            数字转键,
+        }
+    }
+
+    /// 构词规则或选项文件有误时返回错误
+    pub fn 新建(输入: &默认输入) -> Result<Self, 错误> {
+        let 布局 = 输入.配置.form.clone();
+        let 原始决策 = 布局.mapping;
+        let mut 原始决策空间 = 布局.mapping_space.unwrap_or_default();
+        合并初始决策(&mut 原始决策空间, &原始决策);
+        let (所有元素, _) = 拓扑排序(&原始决策空间).unwrap();
+        let 棱镜 = Self::生成棱镜(&所有元素);
         let mut 声母列表 = vec![];
         let mut 韵母列表 = vec![];
         let mut 字根列表 = vec![];
@@ -229,13 +524,6 @@ impl 冰雪二拼上下文 {
             声调: [usize::MAX; 声调总数],
             字根: FxHashMap::default(),
         };
-        let 棱镜 = 棱镜 {
-            进制,
-            元素转数字,
-            数字转元素,
-            键转数字,
-            数字转键,
-        };
         for (元素名称, 安排) in &原始决策 {
             let 序号 = 棱镜.元素转数字[元素名称];
             if 元素名称.starts_with("冰声") {
@@ -271,39 +559,67 @@ impl 冰雪二拼上下文 {
             }
         }
         let mut 信息列表 = vec![];
+        let mut 多字词 = vec![];
         for 原始信息 in &输入.词列表 {
+            let 原始序列: Vec<_> = 原始信息.sequence.split(' ').collect();
             let 字符列表: Vec<char> = 原始信息.name.chars().collect();
-            let 原始序列: Vec<_> = 原始信息
-                .sequence
-                .split(' ')
-                .map(|x| x.to_string())
-                .collect();
-            let mut 序列 = [0; 4];
-            for (i, 元素名称) in 原始序列
-                .iter()
-                .filter(|x| !["q", "w", "e", "r"].contains(&x.as_str()))
-                .enumerate()
-            {
-                let 元素编号 = 棱镜.元素转数字.get(元素名称).unwrap();
-                序列[i] = *元素编号;
+            if 字符列表.len() == 1 {
+                信息列表.push(冰雪二拼信息::解析(
+                    字符列表[0],
+                    原始信息.frequency,
+                    &原始序列,
+                    &棱镜,
+                ));
+            } else {
+                多字词.push((字符列表, 原始信息.frequency, 原始序列));
             }
-            let (独立一, 独立二) = match 原始序列[原始序列.len() - 1].as_str() {
-                "q" => (true, true),
-                "w" => (false, true),
-                "e" => (true, false),
-                "r" => (false, false),
-                _ => unreachable!(),
+        }
+        信息列表.sort_by(|a, b| b.频率.cmp(&a.频率));
+        // 词的序列是各字的序列依次连接，每个字以独立标记结尾；对不上时按字查找最常用读音的序列
+        let 单字序号: FxHashMap<char, usize> = 信息列表
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(序号, 信息)| (信息.词, 序号))
+            .collect();
+        let 选项 = 冰雪二拼选项::读取()?;
+        let mut 词信息列表 = vec![];
+        let mut 忽略词列表 = vec![];
+        for (字符列表, 频率, 原始序列) in 多字词 {
+            let 分段: Vec<_> = 原始序列.split_inclusive(|x| 独立标记.contains(x)).collect();
+            let 字: Option<Vec<_>> = if 分段.len() == 字符列表.len() {
+                Some(
+                    zip(&字符列表, 分段)
+                        .map(|(字, 序列)| 冰雪二拼信息::解析(*字, 频率, 序列, &棱镜))
+                        .collect(),
+                )
+            } else {
+                字符列表
+                    .iter()
+                    .map(|x| 单字序号.get(x).map(|序号| 信息列表[*序号].clone()))
+                    .collect()
+            };
+            let Some(字) = 字 else {
+                忽略词列表.push(字符列表.iter().collect::<String>());
+                continue;
             };
-            信息列表.push(冰雪二拼信息 {
-                词: 字符列表[0],
-                序列,
-                独立一,
-                独立二,
-                频率: 原始信息.frequency,
+            词信息列表.push(冰雪二拼词信息 {
+                词: 字符列表.iter().collect(),
+                频率,
+                规则: 选项.构词规则.查找(字.len())?,
+                字,
             });
         }
-        信息列表.sort_by(|a, b| b.频率.cmp(&a.频率));
-        Self {
+        if !忽略词列表.is_empty() {
+            println!(
+                "{} 个词的序列无法解析，已忽略：{}",
+                忽略词列表.len(),
+                忽略词列表.join("、")
+            );
+        }
+        词信息列表.sort_by(|a, b| b.频率.cmp(&a.频率));
+        let 声母约束 = 选项.声母布局.解析(&棱镜, &初始决策)?;
+        Ok(Self {
             配置: 输入.配置.clone(),
             声母列表,
             韵母列表,
@@ -311,7 +627,205 @@ impl 冰雪二拼上下文 {
             韵母声调映射,
             初始决策,
             信息列表,
+            词信息列表,
+            独立规则: 选项.独立规则,
+            声母约束,
             棱镜,
+            原始键位分布信息: 输入.原始键位分布信息.clone(),
+            原始当量信息: 输入.原始当量信息.clone(),
+        })
+    }
+
+    /// 没有导出配置时生成的文件
+    pub const 默认导出器: &[&str] = &["测评", "大竹"];
+
+    fn 转编码(&self, 编码: 编码) -> String {
+        编码
+            .iter()
+            .filter(|x| **x != 0)
+            .map(|x| self.棱镜.数字转键[&(*x as u64)])
+            .collect()
+    }
+
+    /// 单字在前，多字词在后
+    pub fn 生成码表(&self, 编码器: &冰雪二拼编码器) -> Vec<码表项> {
+        let 单字 = zip(&self.信息列表, &编码器.编码结果).map(|(x, y)| (x.词.to_string(), y));
+        let 多字 = zip(&self.词信息列表, &编码器.多字编码结果).map(|(x, y)| (x.词.clone(), y));
+        单字
+            .chain(多字)
+            .map(|(词, 编码信息)| 码表项 {
+                name: 词,
+                full: self.转编码(编码信息.全码),
+                full_rank: 编码信息.候选位置,
+                short: self.转编码(编码信息.简码),
+                short_rank: 0,
+            })
+            .collect()
+    }
+
+    /// 字根部分的拆分，声母和韵母不计
+    pub fn 生成拆分表(&self) -> Vec<(String, String)> {
+        let 映射 = get_pua_mapper();
+        self.信息列表
+            .iter()
+            .map(|信息| {
+                let 拆分: String = 信息.序列[2..]
+                    .iter()
+                    .filter(|x| **x != 0)
+                    .flat_map(|x| self.棱镜.数字转元素[x].chars().collect::<Vec<_>>())
+                    .map(|x| 映射.get(&x).cloned().unwrap_or(x))
+                    .collect();
+                (信息.词.to_string(), 拆分)
+            })
+            .collect()
+    }
+
+    pub fn 生成导出码表(
+        &self, 编码器: &冰雪二拼编码器, 码表: &[码表项]
+    ) -> 导出码表 {
+        let mut 条目列表 = vec![];
+        let 编码结果 = 编码器.编码结果.iter().chain(&编码器.多字编码结果);
+        for (码表项, 编码信息) in zip(码表, 编码结果) {
+            条目列表.push(导出条目 {
+                词: 码表项.name.clone(),
+                编码: 码表项.full.clone(),
+                权重: 编码信息.频率,
+                类型: 条目类型::全码,
+            });
+            if 码表项.short != 码表项.full {
+                条目列表.push(导出条目 {
+                    词: 码表项.name.clone(),
+                    编码: 码表项.short.clone(),
+                    权重: 编码信息.频率 * 2 + 1,
+                    类型: 条目类型::简码,
+                });
+            }
+        }
+        导出码表 {
+            名称: "冰雪二拼".to_string(),
+            标识: "snow_erpin".to_string(),
+            条目列表,
+            拆分列表: self.生成拆分表(),
+            ..Default::default()
+        }
+    }
+
+    pub fn 输出码表(
+        &self,
+        输出目录: &PathBuf,
+        编码器: &冰雪二拼编码器,
+        导出器列表: &[String],
+    ) -> Result<Vec<码表项>, 错误> {
+        let 码表 = self.生成码表(编码器);
+        写入文本文件(输出目录.join("code.txt"), &码表);
+        导出(&self.生成导出码表(编码器, &码表), 导出器列表, 输出目录)?;
+        Ok(码表)
+    }
+
+    // 分析单字全码和简码的码长分布，以及前 3000 字、前 3000 词中的全码重码
+    pub fn 分析码表(
+        &self,
+        编码器: &冰雪二拼编码器,
+        码表: &[码表项],
+        路径: &PathBuf,
+    ) -> Result<(), 错误> {
+        let mut 文件 = File::create(路径)?;
+        let 编码结果 = &编码器.编码结果;
+        let (单字码表, 多字码表) = 码表.split_at(编码结果.len());
+        let 总频率: u64 = 编码结果.iter().map(|x| x.频率).sum();
+        writeln!(文件, "# 码长分布\n")?;
+        writeln!(文件, "| 码长 | 全码字数 | 全码频率 | 简码字数 | 简码频率 |")?;
+        writeln!(文件, "| --- | --- | --- | --- | --- |")?;
+        let mut 分布 = [[(0usize, 0u64); 2]; 6];
+        for (码表项, 编码信息) in zip(单字码表, 编码结果) {
+            for (序号, 编码) in [&码表项.full, &码表项.short].into_iter().enumerate() {
+                let 统计 = &mut 分布[编码.chars().count()][序号];
+                统计.0 += 1;
+                统计.1 += 编码信息.频率;
+            }
+        }
+        for (码长, [全码, 简码]) in 分布.iter().enumerate().skip(1) {
+            writeln!(
+                文件,
+                "| {码长} | {} | {:.2}% | {} | {:.2}% |",
+                全码.0,
+                全码.1 as f64 / 总频率 as f64 * 100.0,
+                简码.0,
+                简码.1 as f64 / 总频率 as f64 * 100.0
+            )?;
+        }
+        let 平均码长: f64 = zip(单字码表, 编码结果)
+            .map(|(x, y)| x.short.chars().count() as f64 * y.频率 as f64)
+            .sum::<f64>()
+            / 总频率 as f64;
+        writeln!(文件, "\n简码加权平均码长：{平均码长:.4}")?;
+        // 全码 -> 词列表的映射，单字和多字词分别按各自的总频率计算百万分之频率
+        let 多字总频率: u64 = 编码器.多字编码结果.iter().map(|x| x.频率).sum();
+        let mut 翻转码表: FxHashMap<&str, Vec<(&str, u64)>> = FxHashMap::default();
+        let 编码结果 = 编码结果.iter().map(|x| (x, 总频率));
+        let 多字编码结果 = 编码器.多字编码结果.iter().map(|x| (x, 多字总频率));
+        for (码表项, (编码信息, 总频率)) in zip(码表, 编码结果.chain(多字编码结果))
+        {
+            let 百万分之频率 = 编码信息.频率 as f64 / 总频率 as f64 * 1_000_000.0;
+            翻转码表
+                .entry(&码表项.full)
+                .or_default()
+                .push((&码表项.name, 百万分之频率 as u64));
+        }
+        for (标题, 部分码表) in [("字", 单字码表), ("词", 多字码表)] {
+            writeln!(文件, "\n# 前 3000 {标题}中重码\n")?;
+            for 码表项 in 部分码表.iter().take(3000).filter(|x| x.full_rank != 0) {
+                let 重码组 = &翻转码表[码表项.full.as_str()];
+                let 位置 = 码表项.full_rank as usize;
+                writeln!(
+                    文件,
+                    "- {} {} {}μ：{:?}",
+                    码表项.name,
+                    码表项.full,
+                    重码组[位置].1,
+                    &重码组[..位置]
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 生成决策的键位图：声母、带调韵母、主根、副根（附第二码）和归并
+    pub fn 生成键盘图(&self, 决策: &冰雪二拼决策) -> 键盘图 {
+        let mut 图 = 键盘图::新建("冰雪二拼".to_string());
+        let 名称 = |元素: &元素| self.棱镜.数字转元素[元素].clone();
+        let 字母 = |键: &键| self.棱镜.数字转键[&(*键 as u64)];
+        for (元素, 键) in &决策.声母 {
+            let 声母 = 名称(元素).trim_start_matches("冰声").to_string();
+            图.添加(字母(键), 标注类别::声母, 声母);
+        }
+        for (元素, (不带调韵母, 声调)) in &self.韵母声调映射 {
+            let 键 = 键盘布局[决策.声调[*声调]][决策.韵母[不带调韵母]];
+            let 韵母 = 名称(元素).trim_start_matches("冰韵").to_string();
+            图.添加(键, 标注类别::韵母, 韵母);
+        }
+        let 映射 = 决策.线性化(&self.棱镜, &self.韵母声调映射);
+        for (元素, 安排) in &决策.字根 {
+            let 字形 = 图.字形(&名称(元素));
+            let (键, 类别, 文本) = match 安排 {
+                冰雪二拼字根安排::未选取 => continue,
+                冰雪二拼字根安排::主根(键) => (字母(键), 标注类别::主根, 字形),
+                冰雪二拼字根安排::副根(键一, 键二) => {
+                    (字母(键一), 标注类别::副根, format!("{字形}{}", 字母(键二)))
+                }
+                冰雪二拼字根安排::归并(代表) => {
+                    if 映射[*元素].0 == 0 {
+                        continue;
+                    }
+                    (
+                        字母(&映射[*元素].0),
+                        标注类别::归并,
+                        format!("{字形}→{}", 图.字形(&名称(代表))),
+                    )
+                }
+            };
+            图.添加(键, 类别, 文本);
         }
+        图
     }
 }
@@ -1,7 +1,10 @@
 use crate::snow2::encoder::冰雪二拼编码器;
-use crate::snow2::{冰雪二拼上下文, 冰雪二拼决策, 冰雪二拼字根安排};
+use crate::snow2::{
+    冰雪二拼上下文, 冰雪二拼决策, 冰雪二拼决策变化, 冰雪二拼字根安排
+};
 use chai::encoders::编码器;
 use chai::objectives::default::默认目标函数参数;
+use chai::objectives::metric::键盘布局;
 use chai::objectives::目标函数;
 use chai::{编码, 部分编码信息, 错误};
 use serde::Serialize;
@@ -19,9 +22,10 @@ pub struct 冰雪二拼指标 {
     一字全码选重数: i64,
     一字简码选重率: f64,
     一字简码分级选重数: [i64; 分级数],
-    一字简码码长分布: [f64; 4],
+    一字简码码长分布: [f64; 5],
     一字简码码长: f64,
     多字全码选重率: f64,
+    多字全码选重数: i64,
     组合当量: f64,
     按键分布: HashMap<char, f64>,
     韵母组数: usize,
@@ -29,6 +33,9 @@ pub struct 冰雪二拼指标 {
     单编码字根组数: usize,
     双编码字根组数: usize,
     字根记忆量: f64,
+    /// 开启独立规则时，全码因此改变的字数和频率
+    独立规则影响字数: usize,
+    独立规则影响频率: f64,
 }
 
 impl Display for 冰雪二拼指标 {
@@ -38,6 +45,12 @@ impl Display for 冰雪二拼指标 {
         write!(f, "单编码字根组数：{}；", self.单编码字根组数)?;
         write!(f, "双编码字根组数：{}；", self.双编码字根组数)?;
         // write!(f, "字根记忆量：{:.2}；", self.字根记忆量)?;
+        write!(
+            f,
+            "独立规则影响字数：{}；影响频率：{:.2}%；",
+            self.独立规则影响字数,
+            self.独立规则影响频率 * 100.0
+        )?;
         write!(f, "\n")?;
         write!(
             f,
@@ -56,43 +69,50 @@ impl Display for 冰雪二拼指标 {
         write!(f, "\n")?;
         write!(
             f,
-            "码长：{:.2}；二码：{:.2}%；三码：{:.2}%；四码：{:.2}%；五码：{:.2}%；",
+            "码长：{:.2}；一码：{:.2}%；二码：{:.2}%；三码：{:.2}%；四码：{:.2}%；五码：{:.2}%；",
             self.一字简码码长,
             self.一字简码码长分布[0] * 100.0,
             self.一字简码码长分布[1] * 100.0,
             self.一字简码码长分布[2] * 100.0,
-            self.一字简码码长分布[3] * 100.0
+            self.一字简码码长分布[3] * 100.0,
+            self.一字简码码长分布[4] * 100.0
+        )?;
+        write!(f, "\n")?;
+        write!(
+            f,
+            "一字简码选重率：{:.4}%；选重数：{}；",
+            self.一字简码选重率 * 100.0,
+            self.一字简码分级选重数.iter().sum::<i64>()
         )?;
-        // write!(
-        //     f,
-        //     "一字简码选重率：{:.4}%；选重数：{}；",
-        //     self.一字简码选重率 * 100.0,
-        //     self.一字简码分级选重数.iter().sum::<i64>()
-        // )?;
-        // for 分级 in 0..分级数 {
-        //     write!(
-        //         f,
-        //         "{} 选重：{}；",
-        //         (分级 + 1) * 分级大小,
-        //         self.一字简码分级选重数[分级]
-        //     )?;
-        // }
-        // write!(f, "\n")?;
-        // write!(f, "多字全码选重率：{:.4}%；", self.多字全码选重率 * 100.0)?;
-        // write!(f, "组合当量：{:.2}；", self.组合当量)?;
-        // write!(f, "用指分布：")?;
-        // for 行 in 键盘布局.iter() {
-        //     if 行.iter().any(|x| self.按键分布.contains_key(x)) {
-        //         f.write_str("\n")?;
-        //         let mut buffer = vec![];
-        //         for 键 in 行 {
-        //             if let Some(频率) = self.按键分布.get(键) {
-        //                 buffer.push(format!("{} {:5.2}%", 键, 频率 * 100.0));
-        //             }
-        //         }
-        //         f.write_str(&buffer.join(" | "))?;
-        //     }
-        // }
+        for 分级 in 0..分级数 {
+            write!(
+                f,
+                "{} 选重：{}；",
+                (分级 + 1) * 分级大小,
+                self.一字简码分级选重数[分级]
+            )?;
+        }
+        write!(f, "\n")?;
+        write!(
+            f,
+            "多字全码选重率：{:.4}%；选重数：{}；",
+            self.多字全码选重率 * 100.0,
+            self.多字全码选重数
+        )?;
+        write!(f, "组合当量：{:.2}；", self.组合当量)?;
+        write!(f, "用指分布：")?;
+        for 行 in 键盘布局.iter() {
+            if 行.iter().any(|x| self.按键分布.contains_key(x)) {
+                f.write_str("\n")?;
+                let mut buffer = vec![];
+                for 键 in 行 {
+                    if let Some(频率) = self.按键分布.get(键) {
+                        buffer.push(format!("{} {:5.2}%", 键, 频率 * 100.0));
+                    }
+                }
+                f.write_str(&buffer.join(" | "))?;
+            }
+        }
         f.write_str("\n")
     }
 }
@@ -110,7 +130,10 @@ pub struct 冰雪二拼缓存 {
     一字简码总选重频数: i64,
     一字简码分级选重个数: [i64; 分级数],
     多字全码总选重频数: i64,
-    长度分界点: [u64; 5],
+    多字全码选重个数: i64,
+    /// 一字简码按码长（1 到 5）统计的频数
+    一字简码码长频数: [i64; 5],
+    长度分界点: [u64; 6],
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -122,7 +145,7 @@ pub enum 编码类型 {
 
 impl 冰雪二拼缓存 {
     pub fn 新建(进制: u64) -> Self {
-        let 长度分界点 = [0, 1, 2, 3, 4].map(|x| 进制.pow(x));
+        let 长度分界点 = [0, 1, 2, 3, 4, 5].map(|x| 进制.pow(x));
         Self {
             进制,
             一字总频数: 0,
@@ -135,6 +158,8 @@ impl 冰雪二拼缓存 {
             一字简码总选重频数: 0,
             一字简码分级选重个数: [0; 分级数],
             多字全码总选重频数: 0,
+            多字全码选重个数: 0,
+            一字简码码长频数: [0; 5],
             长度分界点,
         }
     }
@@ -188,10 +213,11 @@ impl 冰雪二拼缓存 {
     ) {
         use 编码类型::*;
         let 有向频数 = 频数 as i64 * 正负号;
-        if 类型 != 多字全码 {
-            self.一字总频数 += 有向频数;
-        } else {
-            self.多字总频数 += 有向频数;
+        // 每个字的全码和简码各处理一次，总频数只在全码上计
+        match 类型 {
+            一字全码 => self.一字总频数 += 有向频数,
+            多字全码 => self.多字总频数 += 有向频数,
+            一字简码 => {}
         }
         // 手感（使用多字全码和一字简码）
         if 类型 != 一字全码 {
@@ -202,10 +228,18 @@ impl 冰雪二拼缓存 {
                 self.按键数向量[键 as usize] += 有向频数;
                 剩余编码 /= self.进制;
             }
-            // 2. 组合当量
+            // 2. 组合当量，编码最长有五码，当量信息只存放相邻两码的当量
             let 编码长度 = self.长度分界点.iter().position(|&x| 编码 < x).unwrap() as i64;
             self.总组合数 += (编码长度 - 1) * 有向频数;
-            self.总组合当量 += 参数.当量信息[编码 as usize] * 有向频数 as f64;
+            let mut 剩余编码 = 编码;
+            while 剩余编码 >= self.进制 {
+                let 组合 = 剩余编码 % (self.进制 * self.进制);
+                self.总组合当量 += 参数.当量信息[组合 as usize] * 有向频数 as f64;
+                剩余编码 /= self.进制;
+            }
+            if 类型 == 一字简码 {
+                self.一字简码码长频数[编码长度 as usize - 1] += 有向频数;
+            }
         }
         // 离散
         if 选重标记 {
@@ -219,6 +253,7 @@ impl 冰雪二拼缓存 {
                 }
             } else {
                 self.多字全码总选重频数 += 有向频数;
+                self.多字全码选重个数 += 正负号;
             }
         }
     }
@@ -253,10 +288,25 @@ impl 冰雪二拼缓存 {
         // 2. 组合当量
         let 组合当量 = self.总组合当量 / self.总组合数 as f64;
         损失函数 += 组合当量 * 0.05;
-        // 3. 重码
+        // 3. 码长
+        let 一字简码码长分布 = self
+            .一字简码码长频数
+            .map(|x| x as f64 / self.一字总频数 as f64);
+        let 一字简码码长: f64 = 一字简码码长分布
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i + 1) as f64 * x)
+            .sum();
+        损失函数 += 一字简码码长 * 0.01;
+        // 4. 重码
         let 一字全码选重率 = self.一字全码总选重频数 as f64 / self.一字总频数 as f64;
         let 一字简码选重率 = self.一字简码总选重频数 as f64 / self.一字总频数 as f64;
-        let 多字全码选重率 = self.多字全码总选重频数 as f64 / self.多字总频数 as f64;
+        // 还没有词的时候多字全码没有频数
+        let 多字全码选重率 = if self.多字总频数 == 0 {
+            0.0
+        } else {
+            self.多字全码总选重频数 as f64 / self.多字总频数 as f64
+        };
         损失函数 += 多字全码选重率;
         for 分级 in 0..分级数 {
             let 一字全码分级选重率 = self.一字全码分级选重个数[分级] as f64 / 6000.0;
@@ -267,12 +317,13 @@ impl 冰雪二拼缓存 {
         let 指标 = 冰雪二拼指标 {
             一字全码选重率,
             一字全码分级选重数: self.一字全码分级选重个数,
-            一字全码选重数: 0,
+            一字全码选重数: self.一字全码分级选重个数.iter().sum(),
             一字简码选重率,
             一字简码分级选重数: self.一字简码分级选重个数,
-            一字简码码长分布: Default::default(),
-            一字简码码长: 0.0,
+            一字简码码长分布,
+            一字简码码长,
             多字全码选重率,
+            多字全码选重数: self.多字全码选重个数,
             组合当量,
             按键分布,
             韵母组数: 0,
@@ -280,6 +331,8 @@ impl 冰雪二拼缓存 {
             单编码字根组数: 0,
             双编码字根组数: 0,
             字根记忆量: 0.0,
+            独立规则影响字数: 0,
+            独立规则影响频率: 0.0,
         };
         (指标, 损失函数)
     }
@@ -288,27 +341,64 @@ impl 冰雪二拼缓存 {
 pub struct 冰雪二拼目标函数 {
     参数: 默认目标函数参数,
     缓存: 冰雪二拼缓存,
-    编码器: 冰雪二拼编码器,
+    pub 编码器: 冰雪二拼编码器,
+    /// 上一次计算时各字的全码和简码，用于增量地更新缓存
+    全码信息: Vec<部分编码信息>,
+    简码信息: Vec<部分编码信息>,
+    多字全码信息: Vec<部分编码信息>,
+}
+
+/// 把冰雪二拼的五码编码转换为 `进制` 进制的整数，第一码在最低位
+fn 压缩(编码: &[u8; 5], 进制: u64) -> 编码 {
+    编码
+        .iter()
+        .rev()
+        .filter(|x| **x != 0)
+        .fold(0, |结果, x| 结果 * 进制 + *x as u64)
+}
+
+fn 更新(编码信息: &mut 部分编码信息, 实际编码: 编码, 选重标记: bool) {
+    if 编码信息.实际编码 == 实际编码 && 编码信息.选重标记 == 选重标记 {
+        return;
+    }
+    编码信息.上一个实际编码 = 编码信息.实际编码;
+    编码信息.上一个选重标记 = 编码信息.选重标记;
+    编码信息.实际编码 = 实际编码;
+    编码信息.选重标记 = 选重标记;
+    编码信息.有变化 = true;
 }
 
 impl 冰雪二拼目标函数 {
     pub fn 新建(
         上下文: &冰雪二拼上下文, 编码器: 冰雪二拼编码器
     ) -> Result<Self, 错误> {
-        // let 键位分布信息 = 上下文.键位分布信息.clone();
-        // let 当量信息 = 上下文.当量信息.clone();
-        // let 指法计数 = 上下文.棱镜.预处理指法标记(上下文.get_space());
+        let 棱镜 = &上下文.棱镜;
+        let 键位分布信息 = 棱镜.预处理键位分布信息(&上下文.原始键位分布信息);
+        // 五码的编码空间太大，只预处理相邻两码的当量，按 前 + 后 * 进制 索引
+        let mut 当量信息 = vec![0.0; (棱镜.进制 * 棱镜.进制) as usize];
+        for (前, 前键) in &棱镜.数字转键 {
+            for (后, 后键) in &棱镜.数字转键 {
+                let 组合: String = [*前键, *后键].iter().collect();
+                当量信息[(前 + 后 * 棱镜.进制) as usize] =
+                    上下文.原始当量信息.get(&组合).copied().unwrap_or(0.0);
+            }
+        }
         let 参数 = 默认目标函数参数 {
-            键位分布信息: Default::default(),
-            当量信息: Default::default(),
+            键位分布信息,
+            当量信息,
             指法计数: Default::default(),
-            数字转键: 上下文.棱镜.数字转键.clone(),
+            数字转键: 棱镜.数字转键.clone(),
             正则化强度: 1.0,
         };
+        let 词数 = 编码器.编码结果.len();
+        let 多字词数 = 编码器.多字编码结果.len();
         Ok(Self {
             参数,
             编码器,
-            缓存: 冰雪二拼缓存::新建(上下文.棱镜.进制),
+            缓存: 冰雪二拼缓存::新建(棱镜.进制),
+            全码信息: vec![部分编码信息::default(); 词数],
+            简码信息: vec![部分编码信息::default(); 词数],
+            多字全码信息: vec![部分编码信息::default(); 多字词数],
         })
     }
 }
@@ -317,87 +407,76 @@ impl 目标函数 for 冰雪二拼目标函数 {
     type 目标值 = 冰雪二拼指标;
     type 决策 = 冰雪二拼决策;
 
-    fn 计算(&mut self, 决策: &Self::决策, 变化: &Option<()>) -> (Self::目标值, f64) {
+    fn 计算(
+        &mut self,
+        决策: &Self::决策,
+        变化: &Option<冰雪二拼决策变化>,
+    ) -> (Self::目标值, f64) {
+        use 编码类型::*;
         self.编码器.编码(决策, 变化, &mut []);
-        let mut 一字总频率 = 0;
-        let mut 一字全码选重频率 = 0;
-        let mut 一字总稳健频率 = 0.0;
-        let mut 一字全码稳健选重频率 = 0.0;
-        let mut 一字简码总键数 = [0; 4];
-        let mut 一字全码分级选重数 = [0; 分级数];
-
-        for (索引, 编码信息) in self.编码器.编码结果.iter_mut().enumerate() {
-            一字总频率 += 编码信息.频率;
-            一字总稳健频率 += 编码信息.指数频率;
-            if 编码信息.选重 {
-                一字全码选重频率 += 编码信息.频率;
-                一字全码稳健选重频率 += 编码信息.指数频率;
-                let 分级 = 索引 / 分级大小;
-                一字全码分级选重数[分级] += 1;
+        let 进制 = self.缓存.进制;
+        let 字数 = self.编码器.编码结果.len();
+        // 编码器记下了这次全码、简码或重码位置有变化的字词，只需要更新它们
+        for &序号 in &self.编码器.变化序号 {
+            if 序号 >= 字数 {
+                let 索引 = 序号 - 字数;
+                let 编码信息 = &self.编码器.多字编码结果[索引];
+                更新(
+                    &mut self.多字全码信息[索引],
+                    压缩(&编码信息.全码, 进制),
+                    编码信息.选重,
+                );
+                self.缓存.处理(
+                    多字全码,
+                    索引,
+                    编码信息.频率,
+                    &mut self.多字全码信息[索引],
+                    &self.参数,
+                );
+                continue;
             }
-            let 简码 = &编码信息.简码;
-            let 编码长度 = if 简码[4] != 0 {
-                5
-            } else if 简码[3] != 0 {
-                4
-            } else if 简码[2] != 0 {
-                3
-            } else {
-                2
-            };
-            一字简码总键数[编码长度 - 2] += 编码信息.频率;
-
-            // let 编码信息 {
-            //     频率, 全码, 简码,
-            // ..
-            // } = 编码信息;
-            // if 编码信息.词长 == 1 {
-            //     self.缓存缓冲.处理(一字全码, 索引, *频率, 全码, &self.参数);
-            //     self.缓存缓冲.处理(一字简码, 索引, *频率, 简码, &self.参数);
-            // } else {
-            //     self.缓存缓冲.处理(多字全码, 索引, *频率, 全码, &self.参数);
-            // }
+            let 索引 = 序号;
+            let 编码信息 = &self.编码器.编码结果[索引];
+            let 全码 = 压缩(&编码信息.全码, 进制);
+            // 简码都是唯一的，只有没有简码、退回到全码时才可能选重
+            let 简码选重 = 编码信息.选重 && 编码信息.简码 == 编码信息.全码;
+            更新(&mut self.全码信息[索引], 全码, 编码信息.选重);
+            更新(
+                &mut self.简码信息[索引],
+                压缩(&编码信息.简码, 进制),
+                简码选重,
+            );
+            self.缓存.处理(
+                一字全码,
+                索引,
+                编码信息.频率,
+                &mut self.全码信息[索引],
+                &self.参数,
+            );
+            self.缓存.处理(
+                一字简码,
+                索引,
+                编码信息.频率,
+                &mut self.简码信息[索引],
+                &self.参数,
+            );
         }
-        let 一字全码选重数: i64 = 一字全码分级选重数.iter().sum();
-        let 一字简码码长分布 = 一字简码总键数.map(|键数| 键数 as f64 / 一字总频率 as f64);
-        let 一字简码码长: f64 = 一字简码码长分布
-            .iter()
-            .enumerate()
-            .map(|(i, &键数)| (i as f64 + 2.0) * 键数 as f64)
-            .sum();
-        let mut 单编码字根组数 = 0;
-        let mut 双编码字根组数 = 0;
+        let (mut 指标, 损失函数) = self.缓存.汇总(&self.参数);
+        指标.独立规则影响字数 = self.编码器.独立规则影响字数;
+        指标.独立规则影响频率 =
+            self.编码器.独立规则影响频数 as f64 / self.缓存.一字总频数 as f64;
         for 安排 in 决策.字根.values() {
             match 安排 {
                 冰雪二拼字根安排::主根(_) => {
-                    单编码字根组数 += 1;
+                    指标.单编码字根组数 += 1;
                 }
                 冰雪二拼字根安排::副根(_, _) => {
-                    双编码字根组数 += 1;
+                    指标.双编码字根组数 += 1;
                 }
                 _ => {}
             }
         }
-        let 一字全码选重率 = 一字全码选重频率 as f64 / 一字总频率 as f64;
-        let 一字全码稳健选重率 = 一字全码稳健选重频率 / 一字总稳健频率;
-        let 指标 = 冰雪二拼指标 {
-            一字全码选重率,
-            一字全码分级选重数,
-            一字全码选重数,
-            一字简码选重率: 0.0,
-            一字简码分级选重数: [0; 分级数],
-            一字简码码长分布,
-            一字简码码长,
-            多字全码选重率: 0.0,
-            组合当量: 0.0,
-            按键分布: HashMap::new(),
-            韵母组数: 0,
-            字根数: 决策.字根.len(),
-            单编码字根组数,
-            双编码字根组数,
-            字根记忆量: 0.0,
-        };
-        let 损失函数 = 一字全码稳健选重率 + 一字简码码长 * 0.01;
+        指标.字根数 = 决策.字根.len();
         return (指标, 损失函数);
     }
 }
@@ -1,17 +1,24 @@
 //! 冰雪二拼的优化问题。
 
+use chai::objectives::metric::键盘布局;
 use chai::operators::变异;
-use chai::棱镜;
+use chai::{元素, 棱镜};
+use rand::rngs::StdRng;
 use rand::seq::{IndexedRandom, IteratorRandom};
-use rand::{random, rng};
+use rand::{Rng, SeedableRng};
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::snow2::{
-    冰雪二拼上下文, 冰雪二拼决策, 冰雪二拼字根安排, 声调总数, 小, 小集合, 键
+    冰雪二拼上下文, 冰雪二拼决策, 冰雪二拼决策变化, 冰雪二拼声母约束, 冰雪二拼字根安排, 声调总数,
+    小, 小集合, 键,
 };
 
 pub struct 冰雪二拼操作 {
     pub 棱镜: 棱镜,
     pub 小集合键: [键; 小],
+    pub 声母列表: Vec<元素>,
+    pub 声母约束: 冰雪二拼声母约束,
+    rng: StdRng,
 }
 
 #[derive(PartialEq)]
@@ -24,8 +31,18 @@ pub enum 策略 {
 impl 变异 for 冰雪二拼操作 {
     type 决策 = 冰雪二拼决策;
 
-    fn 变异(&mut self, 决策: &mut 冰雪二拼决策) {
-        let 随机数: f64 = random();
+    fn 变异(&mut self, 决策: &mut 冰雪二拼决策) -> 冰雪二拼决策变化 {
+        if self.声母约束.启用 && self.rng.random::<f64>() < 0.1 {
+            let 随机数: f64 = self.rng.random();
+            return if 随机数 < 0.5 {
+                self.随机移动声母(决策)
+            } else if 随机数 < 0.8 {
+                self.随机交换声母(决策)
+            } else {
+                self.随机整键移动声母(决策)
+            };
+        }
+        let 随机数: f64 = self.rng.random();
         if 随机数 < 0.1 {
             self.随机移动韵母(决策)
         } else if 随机数 < 0.2 {
@@ -41,143 +58,217 @@ impl 变异 for 冰雪二拼操作 {
 }
 
 impl 冰雪二拼操作 {
-    pub fn 新建(上下文: &冰雪二拼上下文) -> Self {
+    pub fn 新建(上下文: &冰雪二拼上下文, 种子: u64) -> Self {
         let 小集合键 = 小集合.map(|x| 上下文.棱镜.键转数字[&x] as 键);
         Self {
             棱镜: 上下文.棱镜.clone(),
             小集合键,
+            声母列表: 上下文.声母列表.clone(),
+            声母约束: 上下文.声母约束.clone(),
+            rng: StdRng::seed_from_u64(种子),
+        }
+    }
+
+    /// 声母按键分组，只包含可以移动的声母
+    fn 声母分组(&self, 决策: &冰雪二拼决策) -> FxHashMap<键, Vec<元素>> {
+        let mut 分组: FxHashMap<键, Vec<元素>> = FxHashMap::default();
+        for 声母 in &self.声母列表 {
+            分组.entry(决策.声母[声母]).or_default();
+            if !self.声母约束.固定声母.contains(声母) {
+                分组.get_mut(&决策.声母[声母]).unwrap().push(*声母);
+            }
         }
+        分组
     }
 
-    // pub fn 随机移动声母(&self, 决策: &mut 冰雪二拼决策) -> Vec<元素> {
-    //     let mut rng = rng();
-    //     let mut 声母逆决策 = FxHashMap::default();
-    //     for 声母 in self.元素分类.声母列表.iter() {
-    //         声母逆决策.entry(决策[*声母]).or_insert(vec![]).push(*声母);
-    //     }
-    //     assert!(声母逆决策.len() == 大集合大小);
-    //     let mut 可移动声母 = vec![];
-    //     for 声母列表 in 声母逆决策.values() {
-    //         if 声母列表.len() > 1 {
-    //             for 声母 in 声母列表 {
-    //                 let 声母名 = self.数字转元素[声母][7..].to_string();
-    //                 if 固定声母.contains(&声母名.as_str()) {
-    //                     可移动声母.push(*声母);
-    //                 }
-    //             }
-    //         }
-    //     }
-    //     let 声母 = *可移动声母.choose(&mut rng).unwrap();
-    //     let 声母键 = *声母逆决策.keys().choose(&mut rng).unwrap();
-    //     决策[声母] = 声母键;
-    //     vec![声母]
-    // }
-
-    // pub fn 随机交换声母(&self, 决策: &mut 冰雪二拼决策) -> Vec<元素> {
-    //     let mut rng = rng();
-    //     let 可变声母列表: Vec<_> = self
-    //         .元素分类
-    //         .声母列表
-    //         .iter()
-    //         .filter(|x| {
-    //             let 声母名 = self.数字转元素[*x][7..].to_string();
-    //             固定声母.contains(&声母名.as_str())
-    //         })
-    //         .cloned()
-    //         .collect();
-    //     let 声母一 = *可变声母列表.choose(&mut rng).unwrap();
-    //     let 声母二 = *可变声母列表.choose(&mut rng).unwrap();
-    //     let 键一 = 决策[声母一];
-    //     let 键二 = 决策[声母二];
-    //     决策[声母一] = 键二;
-    //     决策[声母二] = 键一;
-    //     vec![声母一, 声母二]
-    // }
-
-    // pub fn 随机整键移动声母(&mut self, 决策: &mut 冰雪二拼决策) -> Vec<元素> {
-    //     let mut rng = rng();
-    //     // sdfghjkl 键必须为声母键
-    //     let 固定声母键: FxHashSet<_> = "sdfghjkl".chars().map(|x| self.键转数字[&x]).collect();
-    //     let 一码顶键: FxHashSet<_> = ",.".chars().map(|x| self.键转数字[&x]).collect();
-    //     let 声母键: FxHashSet<_> = self.元素分类.声母列表.iter().map(|x| 决策[*x]).collect();
-    //     // 避免小集合出现大跨排，因此如果声母键关于中排的反射不在声母键中，则不允许移出
-    //     let 反射 = |x: &u64| {
-    //         let 列 = (x - 1) % 10 + 1;
-    //         let 行 = (x - 1) / 10;
-    //         10 * (2 - 行) + 列
-    //     };
-    //     let 移出键 = 声母键
-    //         .iter()
-    //         .cloned()
-    //         .filter(|x| !固定声母键.contains(x))
-    //         .filter(|x| 声母键.contains(&反射(x)) || 一码顶键.contains(&反射(x)))
-    //         .choose(&mut rng)
-    //         .unwrap();
-    //     // ;,./ 不能是声母键
-    //     let 移入键 = "qazwsxedcrfvtgbyhnujmikolp"
-    //         .chars()
-    //         .map(|x| self.键转数字[&x])
-    //         .filter(|x| !声母键.contains(x))
-    //         .choose(&mut rng)
-    //         .unwrap();
-    //     let mut 更改的声母 = vec![];
-    //     for 声母 in self.元素分类.声母列表.iter() {
-    //         if 决策[*声母] == 移出键 {
-    //             决策[*声母] = 移入键;
-    //             更改的声母.push(*声母);
-    //         }
-    //     }
-    //     更改的声母
-    // }
-
-    pub fn 随机移动韵母(&self, 决策: &mut 冰雪二拼决策) {
-        let mut rng = rng();
-        let 韵母 = 决策.韵母.keys().choose(&mut rng).cloned().unwrap();
-        决策.韵母.insert(韵母, (0..10).choose(&mut rng).unwrap());
+    /// 键关于中排的反射，例如 q 和 z 互为反射
+    fn 反射(&self, 键: 键) -> Option<键> {
+        let 字母 = self.棱镜.数字转键[&(键 as u64)];
+        let 行号 = (0..3).find(|x| 键盘布局[*x].contains(&字母))?;
+        let 列号 = 键盘布局[行号].iter().position(|x| *x == 字母)?;
+        let 反射字母 = 键盘布局[2 - 行号][列号];
+        self.棱镜.键转数字.get(&反射字母).map(|x| *x as 键)
+    }
+
+    /// 把一个声母移到另一个已有声母的键上，移出的键上还要留有声母
+    pub fn 随机移动声母(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let 分组 = self.声母分组(决策);
+        let mut 声母数: FxHashMap<键, usize> = FxHashMap::default();
+        for 键 in 决策.声母.values() {
+            *声母数.entry(*键).or_default() += 1;
+        }
+        let rng = &mut self.rng;
+        // 按键上的全部声母计数，固定声母也算；只从其中可以移动的声母里选
+        let 可移动声母: Vec<_> = 分组
+            .iter()
+            .filter(|(键, _)| 声母数[*键] > 1)
+            .flat_map(|(_, x)| x)
+            .cloned()
+            .collect();
+        let Some(&声母) = 可移动声母.choose(rng) else {
+            return 冰雪二拼决策变化::无变化();
+        };
+        let 原键 = 决策.声母[&声母];
+        let Some(&新键) = 分组.keys().filter(|x| **x != 原键).choose(rng) else {
+            return 冰雪二拼决策变化::无变化();
+        };
+        决策.声母.insert(声母, 新键);
+        冰雪二拼决策变化 {
+            声母: vec![声母],
+            ..Default::default()
+        }
     }
 
-    pub fn 随机交换韵母(&self, 决策: &mut 冰雪二拼决策) {
-        let mut rng = rng();
-        let 韵母一 = 决策.韵母.keys().choose(&mut rng).cloned().unwrap();
-        let 韵母二 = 决策.韵母.keys().choose(&mut rng).cloned().unwrap();
+    pub fn 随机交换声母(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 可移动声母: Vec<_> = self
+            .声母列表
+            .iter()
+            .filter(|x| !self.声母约束.固定声母.contains(*x))
+            .cloned()
+            .collect();
+        let 选择: Vec<_> = 可移动声母.choose_multiple(rng, 2).cloned().collect();
+        if 选择.len() < 2 {
+            return 冰雪二拼决策变化::无变化();
+        }
+        let (声母一, 声母二) = (选择[0], 选择[1]);
+        let (键一, 键二) = (决策.声母[&声母一], 决策.声母[&声母二]);
+        if 键一 == 键二 {
+            return 冰雪二拼决策变化::无变化();
+        }
+        决策.声母.insert(声母一, 键二);
+        决策.声母.insert(声母二, 键一);
+        冰雪二拼决策变化 {
+            声母: vec![声母一, 声母二],
+            ..Default::default()
+        }
+    }
+
+    /// 把一个键上的声母整体移到一个没有声母的可用键上
+    pub fn 随机整键移动声母(
+        &mut self,
+        决策: &mut 冰雪二拼决策,
+    ) -> 冰雪二拼决策变化 {
+        let 声母键: FxHashSet<_> = 决策.声母.values().cloned().collect();
+        let 约束 = &self.声母约束;
+        // 避免小集合出现大跨排，因此如果声母键关于中排的反射不在声母键中，则不允许移出
+        let 可移出键: Vec<_> = self
+            .声母分组(决策)
+            .into_iter()
+            .filter(|(键, 声母列表)| {
+                // 键上有固定声母时不能整键移出
+                let 有固定声母 = 约束.固定声母.iter().any(|x| 决策.声母.get(x) == Some(键));
+                !约束.必用键.contains(键)
+                    && !有固定声母
+                    && !声母列表.is_empty()
+                    && self
+                        .反射(*键)
+                        .is_some_and(|x| 声母键.contains(&x) || 约束.反射例外键.contains(&x))
+            })
+            .collect();
+        let 可移入键: Vec<_> = 约束
+            .可用键
+            .iter()
+            .filter(|x| !声母键.contains(*x))
+            .cloned()
+            .collect();
+        let rng = &mut self.rng;
+        let (Some((_, 声母列表)), Some(&移入键)) = (可移出键.choose(rng), 可移入键.choose(rng))
+        else {
+            return 冰雪二拼决策变化::无变化();
+        };
+        for 声母 in 声母列表 {
+            决策.声母.insert(*声母, 移入键);
+        }
+        冰雪二拼决策变化 {
+            声母: 声母列表.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn 随机移动韵母(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 韵母 = 决策.韵母.keys().choose(rng).cloned().unwrap();
+        决策.韵母.insert(韵母.clone(), (0..10).choose(rng).unwrap());
+        冰雪二拼决策变化 {
+            韵母: vec![韵母],
+            ..Default::default()
+        }
+    }
+
+    pub fn 随机交换韵母(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 韵母一 = 决策.韵母.keys().choose(rng).cloned().unwrap();
+        let 韵母二 = 决策.韵母.keys().choose(rng).cloned().unwrap();
         let (键一, 键二) = (决策.韵母[&韵母一], 决策.韵母[&韵母二]);
-        决策.韵母.insert(韵母一, 键二);
-        决策.韵母.insert(韵母二, 键一);
+        决策.韵母.insert(韵母一.clone(), 键二);
+        决策.韵母.insert(韵母二.clone(), 键一);
+        冰雪二拼决策变化 {
+            韵母: vec![韵母一, 韵母二],
+            ..Default::default()
+        }
     }
 
-    pub fn 随机移动声调(&self, 决策: &mut 冰雪二拼决策) {
-        let mut rng = rng();
-        let 声调 = (0..声调总数).choose(&mut rng).unwrap();
-        决策.声调[声调] = (0..3).choose(&mut rng).unwrap();
+    pub fn 随机移动声调(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 声调 = (0..声调总数).choose(rng).unwrap();
+        决策.声调[声调] = (0..3).choose(rng).unwrap();
+        冰雪二拼决策变化 {
+            声调: vec![声调],
+            ..Default::default()
+        }
     }
 
-    pub fn 随机交换声调(&self, 决策: &mut 冰雪二拼决策) {
-        let mut rng = rng();
-        let 声调 = (0..声调总数).choose_multiple(&mut rng, 2);
+    pub fn 随机交换声调(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 声调 = (0..声调总数).choose_multiple(rng, 2);
         let (声调一, 声调二) = (声调[0], 声调[1]);
         let (键一, 键二) = (决策.声调[声调一], 决策.声调[声调二]);
         决策.声调[声调一] = 键二;
         决策.声调[声调二] = 键一;
+        冰雪二拼决策变化 {
+            声调: vec![声调一, 声调二],
+            ..Default::default()
+        }
     }
 
-    pub fn 随机移动字根(&self, 决策: &mut 冰雪二拼决策) {
-        let mut rng = rng();
-        let (字根, _) = 决策
+    pub fn 随机移动字根(
+        &mut self, 决策: &mut 冰雪二拼决策
+    ) -> 冰雪二拼决策变化 {
+        let rng = &mut self.rng;
+        let 字根 = *决策
             .字根
             .iter()
             .filter(|(_, v)| matches!(v, 冰雪二拼字根安排::主根(_) | 冰雪二拼字根安排::副根(_, _)))
-            .choose(&mut rng)
-            .unwrap();
+            .choose(rng)
+            .unwrap()
+            .0;
         let 笔画元素 = ["1", "2", "3", "4", "5"].map(|x| self.棱镜.元素转数字[&x.to_string()]);
-        if 笔画元素.contains(&字根) || random::<f64>() < 0.5 {
-            let 编码 = *self.小集合键.choose(&mut rng).unwrap();
-            决策.字根.insert(字根.clone(), 冰雪二拼字根安排::主根(编码));
+        if 笔画元素.contains(&字根) || rng.random::<f64>() < 0.5 {
+            let 编码 = *self.小集合键.choose(rng).unwrap();
+            决策.字根.insert(字根, 冰雪二拼字根安排::主根(编码));
         } else {
-            let 编码一 = *self.小集合键.choose(&mut rng).unwrap();
-            let 编码二 = *self.小集合键.choose(&mut rng).unwrap();
+            let 编码一 = *self.小集合键.choose(rng).unwrap();
+            let 编码二 = *self.小集合键.choose(rng).unwrap();
             决策
                 .字根
-                .insert(字根.clone(), 冰雪二拼字根安排::副根(编码一, 编码二));
+                .insert(字根, 冰雪二拼字根安排::副根(编码一, 编码二));
+        }
+        冰雪二拼决策变化 {
+            字根: vec![字根],
+            ..Default::default()
         }
     }
 }
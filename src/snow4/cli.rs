@@ -0,0 +1,60 @@
+use crate::common::冰雪命令行参数;
+use crate::snow4::objective::冰雪四拼目标函数;
+use crate::snow4::search::穷举;
+use crate::snow4::{冰雪四拼上下文, 冰雪四拼选项};
+use chai::contexts::上下文;
+use chai::interfaces::command_line::{从命令行参数创建, 命令, 命令行};
+use chai::objectives::目标函数;
+use chai::错误;
+use clap::Parser;
+use std::fs::{File, write};
+use std::io::{Error, ErrorKind, Write};
+
+/// 运行冰雪四拼，`参数` 的第一项是程序名
+pub fn 运行(参数: Vec<String>) -> Result<(), 错误> {
+    let 命令行参数 = 冰雪命令行参数::parse_from(参数);
+    let 参数 = 命令行参数.默认;
+    let 输入 = 从命令行参数创建(&参数);
+    let 选项 = 冰雪四拼选项::读取(命令行参数.options.as_ref())?;
+    let 上下文 = 冰雪四拼上下文::新建(&输入, 选项);
+    match 参数.command {
+        命令::Encode { .. } => {
+            let mut 目标函数 = 冰雪四拼目标函数::新建(&上下文);
+            let (指标, 分数) = 目标函数.计算(&上下文.初始决策, &None);
+            println!("分数：{分数:.4}；{指标}");
+        }
+        // 布局总数不多，优化时穷举所有排列，不使用配置中的退火参数
+        命令::Optimize { threads, .. } => {
+            let 命令行 = 命令行::新建(参数, None);
+            let 保留数 = 上下文.选项.保留方案数;
+            let 结果列表 = 穷举(&上下文, threads, 保留数, 冰雪四拼目标函数::新建);
+            let mut 总结文件 = File::create(命令行.输出目录.join("总结.txt"))?;
+            for (名次, 结果) in 结果列表.iter().enumerate() {
+                let 描述 = format!(
+                    "第 {} 名（排列 {}）分数：{:.4}；{}布局：{}\n",
+                    名次 + 1,
+                    结果.序号,
+                    结果.分数,
+                    结果.指标,
+                    上下文.描述布局(&结果.映射)
+                );
+                if 名次 < 10 {
+                    print!("{描述}");
+                }
+                write!(总结文件, "{描述}")?;
+            }
+            if let Some(最优) = 结果列表.first() {
+                write(
+                    命令行.输出目录.join("最优方案.yaml"),
+                    上下文.序列化(&最优.映射),
+                )?;
+            }
+            println!("输出目录：{}", 命令行.输出目录.display());
+        }
+        _ => {
+            let 消息 = "冰雪四拼只支持 encode 和 optimize 命令";
+            return Err(Error::new(ErrorKind::Unsupported, 消息).into());
+        }
+    }
+    Ok(())
+}
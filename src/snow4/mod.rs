@@ -1,3 +1,224 @@
-
+pub mod cli;
+pub mod objective;
+pub mod search;
 pub mod snow4operators;
-pub use snow4operators::冰雪四拼操作;
\ No newline at end of file
+use crate::common::读取选项;
+use chai::{
+    config::{Mapped, 配置},
+    contexts::上下文,
+    interfaces::默认输入,
+    元素, 原始当量信息, 原始键位分布信息, 棱镜, 错误,
+};
+use chrono::Local;
+use indexmap::IndexMap;
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use serde_yaml::to_string;
+pub use snow4operators::{元素映射, 冰雪四拼操作};
+use std::path::PathBuf;
+
+/// 冰雪四拼的选项，默认从 `snow4/options.yaml` 读取，也可以用 `--options` 指定
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪四拼选项 {
+    /// 穷举搜索后保留分数最低的多少个布局
+    pub 保留方案数: usize,
+    pub 权重: 冰雪四拼权重,
+    pub 键盘: 冰雪四拼键盘选项,
+}
+
+impl Default for 冰雪四拼选项 {
+    fn default() -> Self {
+        Self {
+            保留方案数: 100,
+            权重: Default::default(),
+            键盘: Default::default(),
+        }
+    }
+}
+
+/// 一个按键上有多个字母的手机键盘，例如九键。同一个按键上的字母无法区分，
+/// 按键序列相同的音节或词只能靠选重区分
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪四拼键盘选项 {
+    pub 启用: bool,
+    /// 每个按键上的字母（即布局中的键），没有列出的键各自单独占一个按键
+    pub 按键: Vec<String>,
+}
+
+impl Default for 冰雪四拼键盘选项 {
+    fn default() -> Self {
+        Self {
+            启用: false,
+            按键: ["ABC", "DEF", "GHI", "JKL", "MNO", "PQRS", "TUV", "WXYZ"]
+                .map(|x| x.to_string())
+                .to_vec(),
+        }
+    }
+}
+
+/// 各项指标在分数中的权重，都按音节的编码计算；后四项只在启用键盘时计入
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct 冰雪四拼权重 {
+    pub 组合当量: f64,
+    pub 按键分布偏差: f64,
+    pub 音节选重率: f64,
+    pub 词选重率: f64,
+    /// 音节在按键序列相同的候选中的平均位置，从 1 开始
+    pub 音节候选位置: f64,
+    /// 词在按键序列相同的候选中的平均位置，从 1 开始
+    pub 词候选位置: f64,
+}
+
+impl Default for 冰雪四拼权重 {
+    fn default() -> Self {
+        Self {
+            组合当量: 1.0,
+            按键分布偏差: 1.0,
+            音节选重率: 1.0,
+            词选重率: 1.0,
+            音节候选位置: 0.0,
+            词候选位置: 0.0,
+        }
+    }
+}
+
+impl 冰雪四拼选项 {
+    /// 指定的文件必须存在；没有指定时默认文件可以不存在，此时全部使用默认值
+    pub fn 读取(路径: Option<&PathBuf>) -> Result<Self, 错误> {
+        读取选项(路径, "snow4/options.yaml")
+    }
+}
+
+/// 一个音节（或者词）的元素序列和频率
+#[derive(Clone, Debug)]
+pub struct 冰雪四拼信息 {
+    pub 词: String,
+    pub 频率: u64,
+    pub 序列: Vec<元素>,
+}
+
+#[derive(Clone, Debug)]
+pub struct 冰雪四拼上下文 {
+    pub 配置: 配置,
+    pub 初始决策: 元素映射,
+    pub 棱镜: 棱镜,
+    pub 信息列表: Vec<冰雪四拼信息>,
+    pub 选项: 冰雪四拼选项,
+    pub 原始键位分布信息: 原始键位分布信息,
+    pub 原始当量信息: 原始当量信息,
+}
+
+impl 上下文 for 冰雪四拼上下文 {
+    type 决策 = 元素映射;
+
+    fn 序列化(&self, 决策: &Self::决策) -> String {
+        let mut 新配置 = self.配置.clone();
+        新配置.info.as_mut().unwrap().version =
+            Some(format!("{}", Local::now().format("%Y-%m-%d+%H:%M:%S")));
+        let mut mapping = IndexMap::new();
+        for 元素名称 in self.配置.form.mapping.keys() {
+            let 元素 = self.棱镜.元素转数字[元素名称];
+            let 字母 = self.棱镜.数字转键[&决策.0[元素]];
+            mapping.insert(元素名称.clone(), Mapped::Basic(字母.to_string()));
+        }
+        新配置.form.mapping = mapping;
+        to_string(&新配置).unwrap()
+    }
+}
+
+impl 冰雪四拼上下文 {
+    /// 元素都是拼音字母，每个元素映射到一个键，键用大写字母表示
+    pub fn 新建(输入: &默认输入, 选项: 冰雪四拼选项) -> Self {
+        let 原始决策 = &输入.配置.form.mapping;
+        let mut 元素转数字 = FxHashMap::default();
+        let mut 数字转元素 = FxHashMap::default();
+        let mut 键转数字 = FxHashMap::default();
+        let mut 数字转键 = FxHashMap::default();
+        let mut 所有键: Vec<char> = 原始决策
+            .values()
+            .map(|x| {
+                let Mapped::Basic(编码) = x else {
+                    panic!("冰雪四拼的元素只能映射到单个键");
+                };
+                编码.chars().next().unwrap()
+            })
+            .chain(冰雪四拼操作::所有键())
+            .collect();
+        所有键.sort();
+        所有键.dedup();
+        for (序号, 键) in 所有键.into_iter().enumerate() {
+            键转数字.insert(键, 序号 as u64 + 1);
+            数字转键.insert(序号 as u64 + 1, 键);
+        }
+        let 进制 = 键转数字.len() as u64 + 1;
+        for (序号, 元素名称) in 原始决策.keys().enumerate() {
+            元素转数字.insert(元素名称.clone(), 序号 + 1);
+            数字转元素.insert(序号 + 1, 元素名称.clone());
+        }
+        let 棱镜 = 棱镜 {
+            进制,
+            元素转数字,
+            数字转元素,
+            键转数字,
+            数字转键,
+        };
+        let mut 初始决策 = 元素映射(vec![0; 原始决策.len() + 1]);
+        for (元素名称, 安排) in 原始决策 {
+            let Mapped::Basic(编码) = 安排 else {
+                unreachable!()
+            };
+            let 字母 = 编码.chars().next().unwrap();
+            初始决策.0[棱镜.元素转数字[元素名称]] = 棱镜.键转数字[&字母];
+        }
+        let mut 信息列表 = vec![];
+        for 原始信息 in &输入.词列表 {
+            let 序列: Option<Vec<_>> = 原始信息
+                .sequence
+                .split(' ')
+                .map(|x| 棱镜.元素转数字.get(x).cloned())
+                .collect();
+            let Some(序列) = 序列 else {
+                println!(
+                    "{} 的序列 {} 含有未知的元素，已忽略",
+                    原始信息.name, 原始信息.sequence
+                );
+                continue;
+            };
+            信息列表.push(冰雪四拼信息 {
+                词: 原始信息.name.clone(),
+                频率: 原始信息.frequency,
+                序列,
+            });
+        }
+        信息列表.sort_by(|a, b| b.频率.cmp(&a.频率));
+        Self {
+            配置: 输入.配置.clone(),
+            初始决策,
+            棱镜,
+            信息列表,
+            选项,
+            原始键位分布信息: 输入.原始键位分布信息.clone(),
+            原始当量信息: 输入.原始当量信息.clone(),
+        }
+    }
+
+    /// 只列出映射到的键和初始决策不同的元素，例如 `g→J k→Q`
+    pub fn 描述布局(&self, 决策: &元素映射) -> String {
+        let mut 描述 = vec![];
+        for 元素名称 in self.配置.form.mapping.keys() {
+            let 元素 = self.棱镜.元素转数字[元素名称];
+            if 决策.0[元素] != self.初始决策.0[元素] {
+                let 字母 = self.棱镜.数字转键[&决策.0[元素]];
+                描述.push(format!("{元素名称}→{字母}"));
+            }
+        }
+        if 描述.is_empty() {
+            "与初始布局相同".to_string()
+        } else {
+            描述.join(" ")
+        }
+    }
+}
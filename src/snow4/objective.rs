@@ -0,0 +1,246 @@
+use crate::snow4::{元素映射, 冰雪四拼上下文, 冰雪四拼信息, 冰雪四拼权重};
+use chai::objectives::目标函数;
+use chai::{元素, 原始键位分布信息, 键位分布信息};
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct 冰雪四拼指标 {
+    pub 组合当量: f64,
+    pub 按键分布偏差: f64,
+    /// 启用键盘时才有
+    pub 键盘: Option<冰雪四拼键盘指标>,
+}
+
+/// 按键序列相同的音节（或词）按频率排列，除第一个之外都要选重
+#[derive(Clone, Debug, Serialize)]
+pub struct 冰雪四拼键盘指标 {
+    pub 音节选重率: f64,
+    pub 音节选重数: u64,
+    pub 音节候选位置: f64,
+    pub 词选重率: f64,
+    pub 词选重数: u64,
+    pub 词候选位置: f64,
+}
+
+impl Display for 冰雪四拼指标 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "组合当量：{:.4}，按键分布偏差：{:.2}%",
+            self.组合当量,
+            self.按键分布偏差 * 100.0
+        )?;
+        if let Some(键盘) = &self.键盘 {
+            write!(
+                f,
+                "；音节选重率：{:.2}%，选重数：{}，候选位置：{:.4}；词选重率：{:.2}%，选重数：{}，候选位置：{:.4}",
+                键盘.音节选重率 * 100.0,
+                键盘.音节选重数,
+                键盘.音节候选位置,
+                键盘.词选重率 * 100.0,
+                键盘.词选重数,
+                键盘.词候选位置
+            )?;
+        }
+        writeln!(f)
+    }
+}
+
+/// 按键序列相同的对象中，每个对象的频率和选重情况
+struct 歧义统计 {
+    选重率: f64,
+    选重数: u64,
+    候选位置: f64,
+}
+
+/// 多字母按键的键盘：键（字母）先按布局映射，再归到所在的按键上
+pub struct 冰雪四拼键盘模型 {
+    /// 键到按键的序号，从 1 开始
+    键转按键: Vec<u64>,
+    /// 元素序列相同的单字和词各自合并频率，按频率从高到低排列
+    音节列表: Vec<(Vec<元素>, u64)>,
+    词列表: Vec<(Vec<元素>, u64)>,
+}
+
+impl 冰雪四拼键盘模型 {
+    pub fn 新建(上下文: &冰雪四拼上下文) -> Option<Self> {
+        let 选项 = &上下文.选项.键盘;
+        if !选项.启用 {
+            return None;
+        }
+        let 棱镜 = &上下文.棱镜;
+        let mut 键转按键 = vec![0; 棱镜.进制 as usize];
+        let mut 按键数 = 0;
+        for 字母列表 in &选项.按键 {
+            按键数 += 1;
+            for 字母 in 字母列表.chars() {
+                if let Some(键) = 棱镜.键转数字.get(&字母) {
+                    键转按键[*键 as usize] = 按键数;
+                }
+            }
+        }
+        for 按键 in 键转按键.iter_mut().skip(1).filter(|x| **x == 0) {
+            按键数 += 1;
+            *按键 = 按键数;
+        }
+        let mut 音节频率: FxHashMap<Vec<元素>, u64> = FxHashMap::default();
+        let mut 词频率: FxHashMap<Vec<元素>, u64> = FxHashMap::default();
+        for 信息 in &上下文.信息列表 {
+            let 频率表 = if 信息.词.chars().count() == 1 {
+                &mut 音节频率
+            } else {
+                &mut 词频率
+            };
+            *频率表.entry(信息.序列.clone()).or_default() += 信息.频率;
+        }
+        let 排序 = |频率表: FxHashMap<Vec<元素>, u64>| {
+            let mut 列表: Vec<_> = 频率表.into_iter().collect();
+            列表.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            列表
+        };
+        Some(Self {
+            键转按键,
+            音节列表: 排序(音节频率),
+            词列表: 排序(词频率),
+        })
+    }
+
+    fn 统计(&self, 列表: &[(Vec<元素>, u64)], 决策: &元素映射) -> 歧义统计 {
+        let mut 已有: FxHashMap<Vec<u64>, u64> = FxHashMap::default();
+        let mut 总频率 = 0;
+        let mut 选重频率 = 0;
+        let mut 选重数 = 0;
+        let mut 总候选位置 = 0;
+        for (序列, 频率) in 列表 {
+            let 按键序列: Vec<_> = 序列
+                .iter()
+                .map(|x| self.键转按键[决策.0[*x] as usize])
+                .collect();
+            let 位置 = 已有.entry(按键序列).or_default();
+            总频率 += 频率;
+            总候选位置 += (*位置 + 1) * 频率;
+            if *位置 > 0 {
+                选重频率 += 频率;
+                选重数 += 1;
+            }
+            *位置 += 1;
+        }
+        歧义统计 {
+            选重率: 选重频率 as f64 / 总频率.max(1) as f64,
+            选重数,
+            候选位置: 总候选位置 as f64 / 总频率.max(1) as f64,
+        }
+    }
+
+    pub fn 计算(&self, 决策: &元素映射) -> 冰雪四拼键盘指标 {
+        let 音节 = self.统计(&self.音节列表, 决策);
+        let 词 = self.统计(&self.词列表, 决策);
+        冰雪四拼键盘指标 {
+            音节选重率: 音节.选重率,
+            音节选重数: 音节.选重数,
+            音节候选位置: 音节.候选位置,
+            词选重率: 词.选重率,
+            词选重数: 词.选重数,
+            词候选位置: 词.候选位置,
+        }
+    }
+}
+
+/// 按音节的编码计算手感指标；键用大写字母表示，查当量和用指分布时转为小写
+pub struct 冰雪四拼目标函数 {
+    pub 信息列表: Vec<冰雪四拼信息>,
+    /// 相邻两码的当量，按 前 + 后 * 进制 索引
+    pub 当量信息: Vec<f64>,
+    pub 键位分布信息: 键位分布信息,
+    pub 进制: u64,
+    pub 权重: 冰雪四拼权重,
+    pub 键盘模型: Option<冰雪四拼键盘模型>,
+}
+
+impl 冰雪四拼目标函数 {
+    pub fn 新建(上下文: &冰雪四拼上下文) -> Self {
+        let 棱镜 = &上下文.棱镜;
+        let mut 当量信息 = vec![0.0; (棱镜.进制 * 棱镜.进制) as usize];
+        for (前, 前键) in &棱镜.数字转键 {
+            for (后, 后键) in &棱镜.数字转键 {
+                let 组合: String = [*前键, *后键]
+                    .iter()
+                    .map(|x| x.to_ascii_lowercase())
+                    .collect();
+                当量信息[(前 + 后 * 棱镜.进制) as usize] =
+                    上下文.原始当量信息.get(&组合).copied().unwrap_or(0.0);
+            }
+        }
+        let 原始键位分布信息: 原始键位分布信息 = 棱镜
+            .数字转键
+            .values()
+            .filter_map(|x| {
+                let 小写 = x.to_ascii_lowercase();
+                上下文.原始键位分布信息.get(&小写).map(|y| (*x, y.clone()))
+            })
+            .collect();
+        Self {
+            信息列表: 上下文.信息列表.clone(),
+            当量信息,
+            键位分布信息: 棱镜.预处理键位分布信息(&原始键位分布信息),
+            进制: 棱镜.进制,
+            权重: 上下文.选项.权重.clone(),
+            键盘模型: 冰雪四拼键盘模型::新建(上下文),
+        }
+    }
+}
+
+impl 目标函数 for 冰雪四拼目标函数 {
+    type 目标值 = 冰雪四拼指标;
+    type 决策 = 元素映射;
+
+    fn 计算(&mut self, 决策: &元素映射, _变化: &Option<()>) -> (冰雪四拼指标, f64) {
+        let mut 总组合数 = 0;
+        let mut 总组合当量 = 0.0;
+        let mut 总键数 = 0;
+        let mut 按键频率 = vec![0; self.进制 as usize];
+        for 信息 in &self.信息列表 {
+            let 编码: Vec<_> = 信息.序列.iter().map(|x| 决策.0[*x]).collect();
+            for 键 in &编码 {
+                按键频率[*键 as usize] += 信息.频率;
+            }
+            总键数 += 编码.len() as u64 * 信息.频率;
+            for 组合 in 编码.windows(2) {
+                总组合当量 +=
+                    self.当量信息[(组合[0] + 组合[1] * self.进制) as usize] * 信息.频率 as f64;
+            }
+            总组合数 += (编码.len() as u64).saturating_sub(1) * 信息.频率;
+        }
+        let mut 按键分布偏差 = 0.0;
+        for (频率, 损失函数) in zip(&按键频率, &self.键位分布信息) {
+            let 差距 = *频率 as f64 / 总键数.max(1) as f64 - 损失函数.ideal;
+            if 差距 > 0.0 {
+                按键分布偏差 += 损失函数.gt_penalty * 差距;
+            } else {
+                按键分布偏差 -= 损失函数.lt_penalty * 差距;
+            }
+        }
+        // 没有两码以上的编码时组合当量记为 0，避免 NaN 打乱穷举结果的排序
+        let 组合当量 = 总组合当量 / 总组合数.max(1) as f64;
+        let 权重 = &self.权重;
+        let mut 分数 = 组合当量 * 权重.组合当量 + 按键分布偏差 * 权重.按键分布偏差;
+        let 键盘 = self.键盘模型.as_ref().map(|x| x.计算(决策));
+        if let Some(键盘) = &键盘 {
+            分数 += 键盘.音节选重率 * 权重.音节选重率
+                + 键盘.词选重率 * 权重.词选重率
+                + 键盘.音节候选位置 * 权重.音节候选位置
+                + 键盘.词候选位置 * 权重.词候选位置;
+        }
+        (
+            冰雪四拼指标 {
+                组合当量,
+                按键分布偏差,
+                键盘,
+            },
+            分数,
+        )
+    }
+}
@@ -0,0 +1,81 @@
+//! 冰雪四拼的布局数量不多，可以穷举所有排列，不需要退火。
+
+use crate::snow4::{元素映射, 冰雪四拼上下文, 冰雪四拼操作};
+use chai::objectives::目标函数;
+use std::ops::Range;
+use std::thread::scope;
+
+#[derive(Clone, Debug)]
+pub struct 穷举结果<T> {
+    /// 排列的序号，可以用 `冰雪四拼操作::应用` 复现
+    pub 序号: usize,
+    pub 分数: f64,
+    pub 指标: T,
+    pub 映射: 元素映射,
+}
+
+/// 把 0..总数 尽量均匀地分成若干段，线程数多于总数时多出的段为空
+pub fn 划分区间(总数: usize, 线程数: usize) -> Vec<Range<usize>> {
+    let 线程数 = 线程数.max(1);
+    (0..线程数)
+        .map(|i| (总数 * i / 线程数)..(总数 * (i + 1) / 线程数))
+        .collect()
+}
+
+/// 在按分数从低到高排好的列表中插入结果，只保留前 `保留数` 个
+fn 插入<T>(列表: &mut Vec<穷举结果<T>>, 结果: 穷举结果<T>, 保留数: usize) {
+    let 位置 = 列表.partition_point(|x| x.分数 <= 结果.分数);
+    if 位置 < 保留数 {
+        列表.insert(位置, 结果);
+        列表.truncate(保留数);
+    }
+}
+
+/// 把所有排列分给各个线程，每个线程用自己的目标函数评价分到的布局，
+/// 最后合并各线程的结果，返回分数最低的 `保留数` 个布局
+pub fn 穷举<O, F>(
+    上下文: &冰雪四拼上下文,
+    线程数: usize,
+    保留数: usize,
+    创建目标函数: F,
+) -> Vec<穷举结果<O::目标值>>
+where
+    O: 目标函数<决策 = 元素映射>,
+    O::目标值: Send,
+    F: Fn(&冰雪四拼上下文) -> O + Sync,
+{
+    let 总数 = 冰雪四拼操作::总数();
+    let 区间列表 = 划分区间(总数, 线程数);
+    let 线程结果: Vec<_> = scope(|s| {
+        let 线程池: Vec<_> = 区间列表
+            .into_iter()
+            .map(|区间| {
+                let 创建目标函数 = &创建目标函数;
+                s.spawn(move || {
+                    let 操作 = 冰雪四拼操作::新建(上下文, 区间.start);
+                    let mut 目标函数 = 创建目标函数(上下文);
+                    let mut 映射 = 上下文.初始决策.clone();
+                    let mut 结果列表 = vec![];
+                    for 序号 in 区间 {
+                        操作.应用(&mut 映射, 序号);
+                        let (指标, 分数) = 目标函数.计算(&映射, &None);
+                        let 结果 = 穷举结果 {
+                            序号,
+                            分数,
+                            指标,
+                            映射: 映射.clone(),
+                        };
+                        插入(&mut 结果列表, 结果, 保留数);
+                    }
+                    结果列表
+                })
+            })
+            .collect();
+        线程池.into_iter().map(|x| x.join().unwrap()).collect()
+    });
+    let mut 结果列表 = vec![];
+    for 结果 in 线程结果.into_iter().flatten() {
+        插入(&mut 结果列表, 结果, 保留数);
+    }
+    结果列表
+}
@@ -1,13 +1,22 @@
 //! 冰雪四拼手机键位布局的优化问题。
 //!
 
-use chai::contexts::default::默认上下文;
-use chai::optimizers::决策;
+use crate::snow4::冰雪四拼上下文;
 use chai::operators::变异;
+use chai::optimizers::决策;
 use chai::{元素, 键};
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 
+const 第一组: [[char; 3]; 4] = [
+    ['G', 'K', 'H'],
+    ['J', 'Q', 'X'],
+    ['Z', 'C', 'S'],
+    ['W', 'Y', 'V'],
+];
+const 第二组: [char; 3] = ['R', 'L', 'F'];
+const 第三组: [char; 5] = ['A', 'E', 'I', 'O', 'U'];
+
 pub struct 冰雪四拼操作 {
     group1: Vec<Vec<[char; 3]>>,
     group2: Vec<Vec<char>>,
@@ -17,8 +26,8 @@ pub struct 冰雪四拼操作 {
     键转数字: FxHashMap<char, 键>,
 }
 
-#[derive(Clone)]
-pub struct 元素映射(Vec<键>);
+#[derive(Clone, Debug)]
+pub struct 元素映射(pub Vec<键>);
 
 impl 决策 for 元素映射 {
     type 变化 = ();
@@ -31,15 +40,61 @@ impl 变异 for 冰雪四拼操作 {
     type 决策 = 元素映射;
 
     fn 变异(&mut self, 映射: &mut 元素映射) {
-        let index1 = self.index % self.group1.len();
-        let index2 = (self.index / self.group1.len()) % self.group2.len();
-        let index3 = (self.index / self.group1.len() / self.group2.len()) % self.group3.len();
-        let info1 = self.group1[index1].clone();
-        let info2 = self.group2[index2].clone();
-        let info3 = self.group3[index3].clone();
+        self.应用(映射, self.index);
+        self.index += 1;
+    }
+}
+
+fn make_permutation<T: Clone>(elements: &Vec<T>) -> Vec<Vec<T>> {
+    let length = elements.len();
+    elements
+        .iter()
+        .permutations(length)
+        .map(|p| p.into_iter().cloned().collect())
+        .collect()
+}
+
+impl 冰雪四拼操作 {
+    /// 从第 `index` 个排列开始枚举
+    pub fn 新建(上下文: &冰雪四拼上下文, index: usize) -> Self {
+        Self {
+            group1: make_permutation(&第一组.to_vec()),
+            group2: make_permutation(&第二组.to_vec()),
+            group3: make_permutation(&第三组.to_vec()),
+            index,
+            元素转数字: 上下文.棱镜.元素转数字.clone(),
+            键转数字: 上下文.棱镜.键转数字.clone(),
+        }
+    }
+
+    /// 排列会用到的所有键，上下文据此建立键的编号
+    pub fn 所有键() -> Vec<char> {
+        第一组
+            .iter()
+            .flatten()
+            .chain(第二组.iter())
+            .chain(第三组.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// 不同布局的总数，即三组键各自排列数的乘积；序号超过总数时从头循环
+    pub fn 总数() -> usize {
+        let 阶乘 = |n: usize| (1..=n).product::<usize>();
+        阶乘(第一组.len()) * 阶乘(第二组.len()) * 阶乘(第三组.len())
+    }
+
+    /// 把映射设为第 `index` 个排列对应的布局
+    pub fn 应用(&self, 映射: &mut 元素映射, index: usize) {
+        let index1 = index % self.group1.len();
+        let index2 = (index / self.group1.len()) % self.group2.len();
+        let index3 = (index / self.group1.len() / self.group2.len()) % self.group3.len();
+        let info1 = &self.group1[index1];
+        let info2 = &self.group2[index2];
+        let info3 = &self.group3[index3];
         // b, p, m, d, t, n 不变
         // g, k, h, j, q, x, z, c, s, w, y, v
-        for (i, elements) in vec![
+        for (i, elements) in [
             ["g", "k", "h"],
             ["j", "q", "x"],
             ["z", "c", "s"],
@@ -54,45 +109,14 @@ impl 变异 for 冰雪四拼操作 {
             }
         }
         // r, l, f
-        for (i, element) in vec!["r", "l", "f"].into_iter().enumerate() {
+        for (i, element) in ["r", "l", "f"].into_iter().enumerate() {
             let repr = self.元素转数字[element];
             映射.0[repr] = self.键转数字[&info2[i]];
         }
         // a, e, i, o, u
-        for (i, element) in vec!["a", "e", "i", "o", "u"].into_iter().enumerate() {
+        for (i, element) in ["a", "e", "i", "o", "u"].into_iter().enumerate() {
             let repr = self.元素转数字[element];
             映射.0[repr] = self.键转数字[&info3[i]];
         }
-        self.index += 1;
-    }
-}
-
-fn make_permutation<T: Clone>(elements: &Vec<T>) -> Vec<Vec<T>> {
-    let length = elements.len();
-    elements
-        .iter()
-        .permutations(length)
-        .map(|p| p.into_iter().cloned().collect())
-        .collect()
-}
-
-impl 冰雪四拼操作 {
-    pub fn new(数据: 默认上下文) -> Self {
-        let group1 = vec![
-            ['G', 'K', 'H'],
-            ['J', 'Q', 'X'],
-            ['Z', 'C', 'S'],
-            ['W', 'Y', 'V'],
-        ];
-        let group2 = vec!['R', 'L', 'F'];
-        let group3 = vec!['A', 'E', 'I', 'O', 'U'];
-        Self {
-            group1: make_permutation(&group1),
-            group2: make_permutation(&group2),
-            group3: make_permutation(&group3),
-            index: 0,
-            元素转数字: 数据.棱镜.元素转数字.clone(),
-            键转数字: 数据.棱镜.键转数字.clone(),
-        }
     }
 }
@@ -0,0 +1,218 @@
+use chai::{
+    config::配置, contexts::条件安排, encoders::编码器, operators::变异, optimizers::决策
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+use snow::feihua::{
+    encoder::{冰雪飞花编码信息, 冰雪飞花编码器},
+    operators::冰雪飞花操作,
+    全集合, 冰雪飞花上下文, 冰雪飞花决策, 冰雪飞花决策空间, 冰雪飞花安排, 冰雪飞花汉字信息,
+    冰雪飞花简码选项, 冰雪飞花部首组, 大集合, 小, 小集合, 键,
+};
+use std::iter::zip;
+
+#[test]
+fn radical_groups_follow_candidate_lists() {
+    // 0 表示数据中没有部首；两个无部首的字列出了不同的候选部首
+    let 候选列表 = vec![
+        vec![0, 1],
+        vec![0, 2],
+        vec![0],
+        vec![3, 1],
+        vec![3],
+        vec![0, 1],
+    ];
+    let (部首组列表, 所属部首组) = 冰雪飞花部首组::划分(&候选列表, |x| x.to_string());
+    assert_ne!(所属部首组[0], 所属部首组[1]);
+    assert_eq!(所属部首组[0], 所属部首组[5]);
+    assert_eq!(所属部首组[2], usize::MAX);
+    assert_eq!(所属部首组[4], usize::MAX);
+    assert_eq!(部首组列表.len(), 3);
+    for (候选, 部首组) in 候选列表.iter().zip(&所属部首组) {
+        if *部首组 != usize::MAX {
+            assert_eq!(&部首组列表[*部首组].候选, 候选);
+        }
+    }
+    assert_eq!(部首组列表[所属部首组[1]].名称, "0/2");
+}
+
+/// 不依赖数据文件的小规模上下文：随机的字、字块和初始决策。
+///
+/// 每个字块的最后一种拆分方式只用固定在小集合上的字根，这样总有可行的拆分，
+/// 增量编码保留原来拆分的情形不会出现
+fn 构造上下文(rng: &mut StdRng) -> 冰雪飞花上下文 {
+    let 声母名称: Vec<_> = (0..12).map(|i| format!("声-{i}")).collect();
+    let 部首名称: Vec<_> = (0..6).map(|i| format!("部{i}")).collect();
+    let 字根名称: Vec<_> = (0..20).map(|i| format!("根{i}")).collect();
+    let 所有元素: Vec<_> = [&声母名称, &部首名称, &字根名称]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let 棱镜 = 冰雪飞花上下文::生成棱镜(&所有元素);
+    let 转数字 =
+        |名称: &[String]| -> Vec<_> { 名称.iter().map(|x| 棱镜.元素转数字[x]).collect() };
+    let (声母列表, 部首列表, 字根列表) = (转数字(&声母名称), 转数字(&部首名称), 转数字(&字根名称));
+    let 大集合键: Vec<_> = 大集合.map(|x| 棱镜.键转数字[&x] as 键).to_vec();
+    let 小集合键: Vec<_> = 小集合.map(|x| 棱镜.键转数字[&x] as 键).to_vec();
+    let 全集合键: Vec<_> = 全集合.map(|x| 棱镜.键转数字[&x] as 键).to_vec();
+    let 键位 = |键列表: &[键]| -> Vec<_> {
+        键列表.iter().map(|x| 冰雪飞花安排::键位(*x)).collect()
+    };
+    let mut 初始决策 = 冰雪飞花决策 {
+        元素: vec![冰雪飞花安排::键位(0); 棱镜.元素转数字.len() + 1],
+        部首: vec![],
+    };
+    let mut 决策空间 = 冰雪飞花决策空间 {
+        元素空间: vec![vec![]; 棱镜.元素转数字.len() + 1],
+    };
+    let mut 设置 = |元素: usize, 安排列表: Vec<冰雪飞花安排>| {
+        初始决策.元素[元素] = 安排列表[0];
+        决策空间.元素空间[元素] = 安排列表
+            .into_iter()
+            .map(|安排| 条件安排 {
+                条件: vec![],
+                安排,
+                分数: 0.0,
+            })
+            .collect();
+    };
+    for 声母 in &声母列表 {
+        设置(*声母, 键位(&大集合键));
+    }
+    for 部首 in &部首列表 {
+        设置(*部首, 键位(&全集合键));
+    }
+    // 前四个字根固定在小集合上，其余字根可以移到大集合上而不可行，或者归并到前面没有归并的字根
+    for (序号, 字根) in 字根列表.iter().enumerate() {
+        let mut 安排列表 = 键位(&小集合键);
+        if 序号 >= 4 {
+            安排列表.push(冰雪飞花安排::键位(*大集合键.choose(rng).unwrap()));
+        }
+        if 序号 >= 10 {
+            安排列表.push(冰雪飞花安排::归并(
+                字根列表[rng.random_range(0..10)],
+            ));
+        }
+        安排列表.rotate_left(rng.random_range(0..小));
+        设置(*字根, 安排列表);
+    }
+    let 动态拆分: Vec<_> = (0..40)
+        .map(|_| {
+            let mut 拆分方式列表: Vec<_> = (0..rng.random_range(0..3))
+                .map(|_| {
+                    let mut 拆分方式 = [0; 3];
+                    for 索引 in 0..rng.random_range(1..=3) {
+                        拆分方式[索引] = *字根列表.choose(rng).unwrap();
+                    }
+                    拆分方式
+                })
+                .collect();
+            拆分方式列表.push([字根列表[rng.random_range(0..4)], 0, 0]);
+            拆分方式列表
+        })
+        .collect();
+    let 候选列表: Vec<_> = (0..250)
+        .map(|_| {
+            let mut 候选 = vec![if rng.random::<f64>() < 0.3 {
+                0
+            } else {
+                *部首列表.choose(rng).unwrap()
+            }];
+            for _ in 0..rng.random_range(0..3) {
+                let 候选部首 = if rng.random::<f64>() < 0.3 {
+                    0
+                } else {
+                    *部首列表.choose(rng).unwrap()
+                };
+                if !候选.contains(&候选部首) {
+                    候选.push(候选部首);
+                }
+            }
+            候选
+        })
+        .collect();
+    let (部首组列表, 所属部首组) = 冰雪飞花部首组::划分(&候选列表, |x| x.to_string());
+    let mut 信息列表 = vec![];
+    for (序号, (候选, 部首组)) in zip(&候选列表, 所属部首组).enumerate() {
+        let 字块 = if rng.random::<f64>() < 0.5 {
+            [rng.random_range(0..动态拆分.len()), usize::MAX]
+        } else {
+            [
+                rng.random_range(0..动态拆分.len()),
+                rng.random_range(0..动态拆分.len()),
+            ]
+        };
+        // 一部分字有两个声母不同的读音
+        let 读音数 = if rng.random::<f64>() < 0.2 { 2 } else { 1 };
+        for 声母 in 声母列表.choose_multiple(rng, 读音数) {
+            信息列表.push(冰雪飞花汉字信息 {
+                汉字: char::from_u32(0x4e00 + 序号 as u32).unwrap(),
+                频率: rng.random_range(1..1000000),
+                声母: *声母,
+                部首: 候选[0],
+                部首组,
+                字块,
+            });
+        }
+    }
+    信息列表.sort_by_key(|x| std::cmp::Reverse(x.频率));
+    初始决策.部首 = vec![0; 部首组列表.len()];
+    冰雪飞花上下文 {
+        配置: 配置::default(),
+        初始决策,
+        决策空间,
+        棱镜,
+        信息列表,
+        动态拆分,
+        部首组列表,
+        简码选项: 冰雪飞花简码选项 {
+            出简字数: 200,
+            三简字数: 100,
+            ..Default::default()
+        },
+        权重: Default::default(),
+        多音字选项: Default::default(),
+        原始键位分布信息: Default::default(),
+        原始当量信息: Default::default(),
+    }
+}
+
+fn 比较(增量: &[冰雪飞花编码信息], 完整: &[冰雪飞花编码信息], 步数: usize) {
+    for (序号, (甲, 乙)) in zip(增量, 完整).enumerate() {
+        assert_eq!(
+            (甲.全码, 甲.简码, 甲.候选位置, 甲.选重),
+            (乙.全码, 乙.简码, 乙.候选位置, 乙.选重),
+            "第 {步数} 步第 {序号} 个字的增量编码与完整编码不同"
+        );
+    }
+}
+
+/// 模拟退火中被拒绝的变异不会撤销编码器的状态，下一次变异时与新的变化合并
+#[test]
+fn incremental_encoding_matches_full_encoding() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let 上下文 = 构造上下文(&mut rng);
+    let mut 操作 = 冰雪飞花操作::新建(&上下文, 42);
+    let mut 增量编码器 = 冰雪飞花编码器::新建(&上下文);
+    let mut 当前决策 = 上下文.初始决策.clone();
+    增量编码器.编码(&当前决策, &None, &mut []);
+    let mut 被拒绝的变化 = None;
+    for 步数 in 0..2000 {
+        let mut 决策 = 当前决策.clone();
+        let 变化 = 操作.变异(&mut 决策);
+        let 变化 = match &被拒绝的变化 {
+            Some(旧变化) => 冰雪飞花决策::除法(旧变化, &变化),
+            None => 变化,
+        };
+        增量编码器.编码(&决策, &Some(变化.clone()), &mut []);
+        let mut 完整编码器 = 冰雪飞花编码器::新建(&上下文);
+        完整编码器.编码(&决策, &None, &mut []);
+        比较(&增量编码器.编码结果, &完整编码器.编码结果, 步数);
+        if rng.random::<f64>() < 0.5 {
+            当前决策 = 决策;
+            被拒绝的变化 = None;
+        } else {
+            被拒绝的变化 = Some(变化);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use snow::qingyun::matching::最大权匹配;
+
+#[test]
+fn matching_beats_greedy() {
+    // 贪心会把第 0 行匹配到权重最大的第 0 列，使第 1 行只能得到 1
+    let 权重 = vec![vec![10, 9, 0], vec![8, 1, 0]];
+    let 匹配 = 最大权匹配(&权重);
+    assert_eq!(匹配, vec![1, 0]);
+    let 总权重: i64 = 匹配.iter().enumerate().map(|(i, &j)| 权重[i][j]).sum();
+    assert_eq!(总权重, 17);
+}
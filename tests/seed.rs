@@ -0,0 +1,13 @@
+use snow::common::派生种子;
+
+#[test]
+fn derived_seeds_are_stable_and_distinct() {
+    let 种子列表: Vec<_> = (0..8).map(|i| 派生种子(42, i)).collect();
+    assert_eq!(种子列表, (0..8).map(|i| 派生种子(42, i)).collect::<Vec<_>>());
+    for i in 0..种子列表.len() {
+        for j in (i + 1)..种子列表.len() {
+            assert_ne!(种子列表[i], 种子列表[j]);
+        }
+    }
+    assert_ne!(派生种子(42, 0), 派生种子(43, 0));
+}
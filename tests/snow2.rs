@@ -0,0 +1,160 @@
+use chai::{config::配置, encoders::编码器, operators::变异, optimizers::决策};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+use rustc_hash::FxHashMap;
+use snow::snow2::{
+    encoder::{冰雪二拼编码信息, 冰雪二拼编码器},
+    operators::冰雪二拼操作,
+    冰雪二拼上下文, 冰雪二拼信息, 冰雪二拼决策, 冰雪二拼声母约束, 冰雪二拼字根安排,
+    冰雪二拼构词规则, 冰雪二拼词信息, 声调总数, 大集合, 小集合, 键,
+};
+use std::iter::zip;
+
+/// 不依赖数据文件的小规模上下文：随机的字、词和初始决策
+fn 构造上下文(rng: &mut StdRng) -> 冰雪二拼上下文 {
+    let 声母名称: Vec<_> = (0..12).map(|i| format!("冰声{i}")).collect();
+    let 韵母名称: Vec<_> = (0..8)
+        .flat_map(|i| (1..=声调总数).map(move |j| format!("冰韵{i}{j}")))
+        .collect();
+    let 字根名称: Vec<_> = (1..=5)
+        .map(|i| i.to_string())
+        .chain((0..20).map(|i| format!("根{i}")))
+        .collect();
+    let 所有元素: Vec<_> = [&声母名称, &韵母名称, &字根名称]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let 棱镜 = 冰雪二拼上下文::生成棱镜(&所有元素);
+    let 转数字 =
+        |名称: &[String]| -> Vec<_> { 名称.iter().map(|x| 棱镜.元素转数字[x]).collect() };
+    let (声母列表, 韵母列表, 字根列表) = (转数字(&声母名称), 转数字(&韵母名称), 转数字(&字根名称));
+    let 大集合键: Vec<_> = 大集合.map(|x| 棱镜.键转数字[&x] as 键).to_vec();
+    let 小集合键: Vec<_> = 小集合.map(|x| 棱镜.键转数字[&x] as 键).to_vec();
+    let 韵母声调映射: FxHashMap<_, _> = zip(
+        &韵母列表,
+        (0..8).flat_map(|i| (0..声调总数).map(move |j| (i.to_string(), j))),
+    )
+    .map(|(元素, 读音)| (*元素, 读音))
+    .collect();
+    let mut 初始决策 = 冰雪二拼决策 {
+        声母: 声母列表
+            .iter()
+            .map(|x| (*x, *大集合键.choose(rng).unwrap()))
+            .collect(),
+        韵母: (0..8)
+            .map(|i| (i.to_string(), rng.random_range(0..10)))
+            .collect(),
+        声调: [0, 1, 2, 0, 1],
+        字根: FxHashMap::default(),
+    };
+    // 只归并到前面没有归并的字根，和拓扑排序后的数据一致
+    for (序号, 字根) in 字根列表.iter().enumerate() {
+        let 安排 = if 序号 >= 10 && rng.random::<f64>() < 0.4 {
+            冰雪二拼字根安排::归并(字根列表[rng.random_range(0..10)])
+        } else if 序号 < 5 || rng.random::<f64>() < 0.5 {
+            冰雪二拼字根安排::主根(*小集合键.choose(rng).unwrap())
+        } else {
+            冰雪二拼字根安排::副根(
+                *小集合键.choose(rng).unwrap(),
+                *小集合键.choose(rng).unwrap(),
+            )
+        };
+        初始决策.字根.insert(*字根, 安排);
+    }
+    let 信息列表: Vec<_> = (0..400)
+        .map(|序号| 冰雪二拼信息 {
+            词: char::from_u32(0x4e00 + 序号).unwrap(),
+            频率: 100000 / (序号 as u64 + 1),
+            序列: [
+                *声母列表.choose(rng).unwrap(),
+                *韵母列表.choose(rng).unwrap(),
+                *字根列表.choose(rng).unwrap(),
+                if rng.random::<f64>() < 0.6 {
+                    *字根列表.choose(rng).unwrap()
+                } else {
+                    0
+                },
+            ],
+            独立一: rng.random(),
+            独立二: rng.random(),
+        })
+        .collect();
+    let 构词规则 = 冰雪二拼构词规则::default();
+    let 词信息列表 = (0..150)
+        .map(|序号| {
+            let 字: Vec<_> = (0..rng.random_range(2..=5))
+                .map(|_| 信息列表[rng.random_range(0..200)].clone())
+                .collect();
+            冰雪二拼词信息 {
+                词: 字.iter().map(|x| x.词).collect(),
+                频率: 50000 / (序号 as u64 + 1),
+                规则: 构词规则.查找(字.len()).unwrap(),
+                字,
+            }
+        })
+        .collect();
+    冰雪二拼上下文 {
+        配置: 配置::default(),
+        初始决策,
+        声母列表,
+        韵母列表,
+        韵母声调映射,
+        字根列表,
+        信息列表,
+        词信息列表,
+        独立规则: true,
+        声母约束: 冰雪二拼声母约束 {
+            启用: true,
+            可用键: 大集合键,
+            ..Default::default()
+        },
+        棱镜,
+        原始键位分布信息: Default::default(),
+        原始当量信息: Default::default(),
+    }
+}
+
+fn 比较(增量: &[冰雪二拼编码信息], 完整: &[冰雪二拼编码信息], 步数: usize) {
+    for (序号, (甲, 乙)) in zip(增量, 完整).enumerate() {
+        assert_eq!(
+            (甲.全码, 甲.简码, 甲.候选位置, 甲.选重),
+            (乙.全码, 乙.简码, 乙.候选位置, 乙.选重),
+            "第 {步数} 步第 {序号} 项的增量编码与完整编码不同"
+        );
+    }
+}
+
+/// 模拟退火中被拒绝的变异不会撤销编码器的状态，下一次变异时与新的变化合并
+#[test]
+fn incremental_encoding_matches_full_encoding() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let 上下文 = 构造上下文(&mut rng);
+    let mut 操作 = 冰雪二拼操作::新建(&上下文, 42);
+    let mut 增量编码器 = 冰雪二拼编码器::新建(&上下文).unwrap();
+    let mut 当前决策 = 上下文.初始决策.clone();
+    增量编码器.编码(&当前决策, &None, &mut []);
+    let mut 被拒绝的变化 = None;
+    for 步数 in 0..2000 {
+        let mut 决策 = 当前决策.clone();
+        let 变化 = 操作.变异(&mut 决策);
+        let 变化 = match &被拒绝的变化 {
+            Some(旧变化) => 冰雪二拼决策::除法(旧变化, &变化),
+            None => 变化,
+        };
+        增量编码器.编码(&决策, &Some(变化.clone()), &mut []);
+        let mut 完整编码器 = 冰雪二拼编码器::新建(&上下文).unwrap();
+        完整编码器.编码(&决策, &None, &mut []);
+        比较(&增量编码器.编码结果, &完整编码器.编码结果, 步数);
+        比较(&增量编码器.多字编码结果, &完整编码器.多字编码结果, 步数);
+        assert_eq!(
+            (增量编码器.独立规则影响字数, 增量编码器.独立规则影响频数),
+            (完整编码器.独立规则影响字数, 完整编码器.独立规则影响频数)
+        );
+        if rng.random::<f64>() < 0.5 {
+            当前决策 = 决策;
+            被拒绝的变化 = None;
+        } else {
+            被拒绝的变化 = Some(变化);
+        }
+    }
+}
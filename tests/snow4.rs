@@ -0,0 +1,15 @@
+use snow::snow4::{search::划分区间, 冰雪四拼操作};
+
+#[test]
+fn ranges_cover_all_permutations_once() {
+    let 总数 = 冰雪四拼操作::总数();
+    // 三组分别排列 4、3、5 项
+    assert_eq!(总数, 24 * 6 * 120);
+    for 线程数 in [1, 3, 7, 20] {
+        let 区间列表 = 划分区间(总数, 线程数);
+        assert_eq!(区间列表.len(), 线程数);
+        let 序号: Vec<_> = 区间列表.into_iter().flatten().collect();
+        assert_eq!(序号, (0..总数).collect::<Vec<_>>());
+    }
+    assert!(划分区间(2, 4).iter().filter(|x| x.is_empty()).count() == 2);
+}